@@ -0,0 +1,366 @@
+use crate::{
+    attribution::SourceStats, components::EditionId, db::entities::edition, sessions::SessionStats,
+};
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use crate::{
+    charts,
+    components::require_admin,
+    db::{
+        db,
+        entities::{daily_edition_reads, daily_page_reads, reads},
+    },
+};
+#[cfg(feature = "server")]
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EditionStats {
+    pub edition: edition::Model,
+    /// Total read time in milliseconds
+    pub read_time: f32,
+    pub unique_readers: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PageStats {
+    /// Zero-based page index, as stored in `reads`
+    pub page: i32,
+    /// Total read time in milliseconds
+    pub read_time: f32,
+    pub unique_readers: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AnalyticsOverview {
+    pub editions: Vec<EditionStats>,
+    pub read_time_chart: String,
+    pub readers_chart: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EditionReport {
+    pub stats: EditionStats,
+    pub pages: Vec<PageStats>,
+    pub page_chart: String,
+    pub readers_over_time_chart: String,
+    pub sources: Vec<SourceStats>,
+    pub sessions: SessionStats,
+}
+
+/// (edition id, total read time, unique readers) for every edition with reads, from the daily rollups
+#[cfg(feature = "server")]
+pub async fn read_totals_per_edition() -> Result<Vec<(EditionId, f32, i64)>, ServerFnError> {
+    daily_edition_reads::Entity::find()
+        .select_only()
+        .column(daily_edition_reads::Column::EditionId)
+        .expr(daily_edition_reads::Column::ReadTime.sum())
+        .expr(daily_edition_reads::Column::NewReaders.sum())
+        .group_by(daily_edition_reads::Column::EditionId)
+        .into_tuple()
+        .all(db())
+        .await
+        .map_err(|err| {
+            ServerFnError::new(format!(
+                "Failed to get total read times and unique reader counts per edition: {err}"
+            ))
+        })
+}
+
+#[cfg(feature = "server")]
+fn minutes(milliseconds: f32) -> f64 {
+    milliseconds as f64 / 60_000.0
+}
+
+#[server]
+#[tracing::instrument(skip_all, err)]
+pub async fn fetch_analytics() -> Result<AnalyticsOverview, ServerFnError> {
+    require_admin().await?;
+    let lang = crate::i18n::get_lang();
+
+    let mut editions = edition::Entity::find()
+        .all(db())
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to get editions: {err}")))?;
+    editions.sort_by_key(|edition| edition.date);
+
+    let totals = read_totals_per_edition().await?;
+
+    let editions = editions
+        .into_iter()
+        .map(|edition| {
+            let (read_time, unique_readers) = totals
+                .iter()
+                .find(|(id, _, _)| *id == edition.id)
+                .map_or((0.0, 0), |(_, read_time, readers)| (*read_time, *readers));
+            EditionStats {
+                edition,
+                read_time,
+                unique_readers,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let read_time_chart = charts::bar_chart(
+        lang.read_time_per_edition_chart(),
+        &editions
+            .iter()
+            .map(|stats| (stats.edition.date.to_string(), minutes(stats.read_time)))
+            .collect::<Vec<_>>(),
+    );
+    let readers_chart = charts::bar_chart(
+        lang.readers_per_edition_chart(),
+        &editions
+            .iter()
+            .map(|stats| (stats.edition.date.to_string(), stats.unique_readers as f64))
+            .collect::<Vec<_>>(),
+    );
+
+    Ok(AnalyticsOverview {
+        editions,
+        read_time_chart,
+        readers_chart,
+    })
+}
+
+#[server]
+#[tracing::instrument(skip_all, fields(id = id), err)]
+pub async fn fetch_edition_analytics(id: EditionId) -> Result<EditionReport, ServerFnError> {
+    require_admin().await?;
+    let lang = crate::i18n::get_lang();
+
+    let db = db();
+
+    let edition = edition::Entity::find_by_id(id)
+        .one(db)
+        .await
+        .map_err(|err| ServerFnError::new(err.to_string()))?
+        .ok_or(ServerFnError::new(format!("Edition {id} not found")))?;
+
+    // (page number, total read time, unique readers)
+    let pages: Vec<(i32, f32, i64)> = daily_page_reads::Entity::find()
+        .select_only()
+        .column(daily_page_reads::Column::PageNumber)
+        .expr(daily_page_reads::Column::ReadTime.sum())
+        .expr(daily_page_reads::Column::NewReaders.sum())
+        .filter(daily_page_reads::Column::EditionId.eq(id))
+        .group_by(daily_page_reads::Column::PageNumber)
+        .order_by_asc(daily_page_reads::Column::PageNumber)
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|err| {
+            ServerFnError::new(format!(
+                "Failed to get read times per page of edition {id}: {err}"
+            ))
+        })?;
+    let pages = pages
+        .into_iter()
+        .map(|(page, read_time, unique_readers)| PageStats {
+            page,
+            read_time,
+            unique_readers,
+        })
+        .collect::<Vec<_>>();
+
+    let days = daily_edition_reads::Entity::find()
+        .filter(daily_edition_reads::Column::EditionId.eq(id))
+        .order_by_asc(daily_edition_reads::Column::Day)
+        .all(db)
+        .await
+        .map_err(|err| {
+            ServerFnError::new(format!("Failed to get daily reads of edition {id}: {err}"))
+        })?;
+
+    let stats = EditionStats {
+        unique_readers: days.iter().map(|day| day.new_readers as i64).sum(),
+        read_time: days.iter().map(|day| day.read_time).sum(),
+        edition,
+    };
+
+    let page_chart = charts::bar_chart(
+        lang.read_time_per_page_chart(),
+        &pages
+            .iter()
+            .map(|page| ((page.page + 1).to_string(), minutes(page.read_time)))
+            .collect::<Vec<_>>(),
+    );
+    let readers_over_time_chart = charts::line_chart(
+        lang.readers_per_day_chart(),
+        lang.days_since_publication(),
+        &days
+            .iter()
+            .map(|day| {
+                (
+                    (day.day - stats.edition.date).whole_days() as f64,
+                    day.readers as f64,
+                )
+            })
+            .collect::<Vec<_>>(),
+    );
+
+    let sources = crate::attribution::sources_per_edition()
+        .await?
+        .remove(&id)
+        .unwrap_or_default();
+
+    let sessions = crate::sessions::sessions_per_edition(Some(id))
+        .await?
+        .remove(&id)
+        .unwrap_or_default();
+
+    Ok(EditionReport {
+        stats,
+        pages,
+        page_chart,
+        readers_over_time_chart,
+        sources,
+        sessions,
+    })
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PageHeat {
+    /// Median read time of a single read in milliseconds
+    pub median: f32,
+    /// Total read time in milliseconds
+    pub total: f32,
+    pub reads: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Heatmap {
+    pub edition: edition::Model,
+    /// Indexed by zero-based page number
+    pub pages: Vec<PageHeat>,
+}
+
+#[cfg(feature = "server")]
+fn median(values: &mut [f32]) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(f32::total_cmp);
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+/// Read times of all reads of an edition, grouped by zero-based page number.
+/// Medians and distributions can't be derived from the daily rollups, so this still scans `reads`.
+#[cfg(feature = "server")]
+async fn read_times_per_page(
+    id: EditionId,
+    num_pages: usize,
+) -> Result<Vec<Vec<f32>>, ServerFnError> {
+    let read_times: Vec<(i32, f32)> = reads::Entity::find()
+        .select_only()
+        .column(reads::Column::PageNumber)
+        .column(reads::Column::ReadTime)
+        .filter(reads::Column::EditionId.eq(id))
+        .into_tuple()
+        .all(db())
+        .await
+        .map_err(|err| {
+            ServerFnError::new(format!("Failed to get read times of edition {id}: {err}"))
+        })?;
+
+    let mut pages = vec![Vec::new(); num_pages];
+    for (page, read_time) in read_times {
+        if let Some(page) = usize::try_from(page)
+            .ok()
+            .and_then(|page| pages.get_mut(page))
+        {
+            page.push(read_time);
+        }
+    }
+    Ok(pages)
+}
+
+#[server]
+#[tracing::instrument(skip_all, fields(id = id), err)]
+pub async fn fetch_heatmap(id: EditionId) -> Result<Heatmap, ServerFnError> {
+    use crate::components::count_pages;
+
+    require_admin().await?;
+
+    let edition = edition::Entity::find_by_id(id)
+        .one(db())
+        .await
+        .map_err(|err| ServerFnError::new(err.to_string()))?
+        .ok_or(ServerFnError::new(format!("Edition {id} not found")))?;
+    let num_pages = count_pages(&edition).await? as usize;
+
+    let pages = read_times_per_page(id, num_pages)
+        .await?
+        .into_iter()
+        .map(|mut read_times| PageHeat {
+            total: read_times.iter().sum(),
+            reads: read_times.len(),
+            median: median(&mut read_times),
+        })
+        .collect();
+
+    Ok(Heatmap { edition, pages })
+}
+
+/// Histogram of the read times of a single page, as svg
+#[server]
+#[tracing::instrument(err)]
+pub async fn fetch_read_time_distribution(
+    id: EditionId,
+    page: usize,
+) -> Result<String, ServerFnError> {
+    // upper bucket bounds in seconds
+    const BUCKETS: [f32; 8] = [5., 10., 20., 30., 60., 120., 180., f32::INFINITY];
+
+    use crate::components::count_pages;
+
+    require_admin().await?;
+    let lang = crate::i18n::get_lang();
+
+    let edition = edition::Entity::find_by_id(id)
+        .one(db())
+        .await
+        .map_err(|err| ServerFnError::new(err.to_string()))?
+        .ok_or(ServerFnError::new(format!("Edition {id} not found")))?;
+    let num_pages = count_pages(&edition).await? as usize;
+    if page >= num_pages {
+        return Err(ServerFnError::new(format!(
+            "Page {page} is out of range, edition {id} has {num_pages} pages"
+        )));
+    }
+
+    let read_times = read_times_per_page(id, page + 1).await?.swap_remove(page);
+
+    let mut counts = [0usize; BUCKETS.len()];
+    for read_time in read_times {
+        let seconds = read_time / 1000.0;
+        if let Some(bucket) = BUCKETS.iter().position(|bound| seconds < *bound) {
+            counts[bucket] += 1;
+        }
+    }
+
+    let bars = BUCKETS
+        .iter()
+        .zip(counts)
+        .map(|(bound, count)| {
+            let label = if bound.is_finite() {
+                format!("<{bound}s")
+            } else {
+                format!(">={}s", BUCKETS[BUCKETS.len() - 2])
+            };
+            (label, count as f64)
+        })
+        .collect::<Vec<_>>();
+
+    Ok(charts::bar_chart(
+        &format!("{} {}", lang.read_time_distribution_chart(), page + 1),
+        &bars,
+    ))
+}
@@ -0,0 +1,392 @@
+//! Attribute readers to where they came from: the referrer, `utm_*` parameters and whether the
+//! edition was opened on Home, from the archive or with a direct link
+
+use crate::{Route, components::EditionId};
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[cfg(feature = "server")]
+use crate::{
+    db::{db, entities::read_sources},
+    track_views::NO_ID,
+};
+#[cfg(feature = "server")]
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+#[cfg(feature = "server")]
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Longest stored `utm_*` value, longer ones are truncated
+#[cfg(feature = "server")]
+const MAX_UTM_LENGTH: usize = 100;
+
+/// The routes rendered under the navbar, updated by the navbar whenever the route changes
+pub static ROUTE_HISTORY: GlobalSignal<RouteHistory> = Signal::global(RouteHistory::default);
+/// Whether an edition was already opened since the page was loaded.
+/// Only the first one gets the referrer and `utm_*` parameters, as they describe the page load.
+static LANDED: GlobalSignal<bool> = Signal::global(|| false);
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EntryPoint {
+    /// The newest edition on Home
+    Home,
+    /// A link in the archive
+    Archive,
+    /// A `/editions/:id` link opened from outside
+    Direct,
+    /// Any other navigation within the site
+    Internal,
+}
+#[cfg(feature = "server")]
+impl EntryPoint {
+    pub const fn to_str(self) -> &'static str {
+        match self {
+            Self::Home => "home",
+            Self::Archive => "archive",
+            Self::Direct => "direct",
+            Self::Internal => "internal",
+        }
+    }
+}
+
+/// The current and the previous route. The navbar's effect runs before the ones of the pages
+/// under it, so the current route may already be the page's own route when it classifies its entry.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RouteHistory {
+    previous: Option<Route>,
+    current: Option<Route>,
+}
+impl RouteHistory {
+    /// Shift the current route into the previous one, if the route changed
+    pub fn visit(&mut self, route: Route) {
+        if self.current.as_ref() != Some(&route) {
+            self.previous = self.current.replace(route);
+        }
+    }
+
+    /// How `route` was entered. Handles both orders of `visit` and this.
+    pub fn entry_point(&self, route: &Route) -> EntryPoint {
+        let from = if self.current.as_ref() == Some(route) {
+            &self.previous
+        } else {
+            &self.current
+        };
+
+        match (route, from) {
+            (Route::Home {}, _) => EntryPoint::Home,
+            (_, None) => EntryPoint::Direct,
+            (_, Some(Route::Archiv {})) => EntryPoint::Archive,
+            (_, Some(_)) => EntryPoint::Internal,
+        }
+    }
+}
+
+/// The `utm_*` query parameters of a link
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Campaign {
+    pub source: Option<String>,
+    pub medium: Option<String>,
+    pub campaign: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SourceStats {
+    pub entry: String,
+    /// Host of the referrer
+    pub referrer: Option<String>,
+    pub campaign: Campaign,
+    pub readers: i64,
+    /// Total read time in milliseconds
+    pub read_time: f32,
+}
+
+/// Record where the reading session of an edition came from, once the component is mounted on
+/// the client
+pub fn use_record_source(edition_id: EditionId, session_id: Uuid) {
+    let route = use_route::<Route>();
+
+    use_effect(move || {
+        let entry = ROUTE_HISTORY.peek().entry_point(&route);
+
+        let landed = std::mem::replace(&mut *LANDED.write(), true);
+        let (referrer, campaign) = if landed {
+            (None, Campaign::default())
+        } else {
+            page_load_source()
+        };
+
+        spawn(async move {
+            let _ = record_source(edition_id, session_id, entry, referrer, campaign).await;
+        });
+    });
+}
+
+/// The referrer and `utm_*` parameters of the page load
+#[cfg(feature = "web")]
+fn page_load_source() -> (Option<String>, Campaign) {
+    use web_sys::{UrlSearchParams, window};
+
+    let Some(window) = window() else {
+        return (None, Campaign::default());
+    };
+
+    let referrer = window
+        .document()
+        .map(|document| document.referrer())
+        .filter(|referrer| !referrer.is_empty());
+
+    let campaign = window
+        .location()
+        .search()
+        .ok()
+        .and_then(|search| UrlSearchParams::new_with_str(&search).ok())
+        .map(|params| Campaign {
+            source: params.get("utm_source"),
+            medium: params.get("utm_medium"),
+            campaign: params.get("utm_campaign"),
+        })
+        .unwrap_or_default();
+
+    (referrer, campaign)
+}
+#[cfg(not(feature = "web"))]
+fn page_load_source() -> (Option<String>, Campaign) {
+    (None, Campaign::default())
+}
+
+/// Only keep the host of a referrer, and drop referrers from this site
+#[cfg(feature = "server")]
+fn referrer_host(referrer: &str) -> Option<String> {
+    use dioxus::{
+        fullstack::{FullstackContext, reqwest::Url},
+        server::http::header,
+    };
+
+    let host = Url::parse(referrer).ok()?.host_str()?.to_string();
+
+    let own_host = FullstackContext::current().and_then(|context| {
+        context
+            .parts_mut()
+            .headers
+            .get(header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .map(|host| host.split(':').next().unwrap_or(host).to_string())
+    });
+
+    (own_host.as_ref() != Some(&host)).then_some(host)
+}
+
+/// Record where a reading session came from. Only the first source of a session is kept.
+#[server]
+#[tracing::instrument(skip(session_id, referrer, campaign), err)]
+pub async fn record_source(
+    edition_id: EditionId,
+    session_id: Uuid,
+    entry: EntryPoint,
+    referrer: Option<String>,
+    campaign: Campaign,
+) -> Result<(), ServerFnError> {
+    use crate::{
+        bots,
+        track_views::{check_flush, tracked_client_id},
+    };
+    use sea_orm::{Set, sea_query::OnConflict};
+
+    let tracked = tracked_client_id();
+    let client_id = tracked.unwrap_or(NO_ID);
+    check_flush(client_id, edition_id).await?;
+
+    let user_agent = bots::user_agent();
+    if bots::classify_view(user_agent.as_deref(), tracked).is_some() {
+        return Ok(());
+    }
+
+    let truncate = |value: Option<String>| {
+        value
+            .filter(|value| !value.is_empty())
+            .map(|value| value.chars().take(MAX_UTM_LENGTH).collect::<String>())
+    };
+
+    let source = read_sources::ActiveModel {
+        client_id: Set(client_id),
+        session_id: Set(session_id),
+        edition_id: Set(edition_id),
+        entry: Set(entry.to_str().to_string()),
+        referrer: Set(referrer.as_deref().and_then(referrer_host)),
+        utm_source: Set(truncate(campaign.source)),
+        utm_medium: Set(truncate(campaign.medium)),
+        utm_campaign: Set(truncate(campaign.campaign)),
+        ..Default::default()
+    };
+
+    read_sources::Entity::insert(source)
+        .on_conflict(
+            OnConflict::columns([
+                read_sources::Column::ClientId,
+                read_sources::Column::SessionId,
+                read_sources::Column::EditionId,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .do_nothing()
+        .exec(db())
+        .await
+        .map(|_| ())
+        .map_err(|err| ServerFnError::new(format!("Failed to record read source: {err}")))
+}
+
+/// Readers and read time of every edition per source.
+/// Readers are attributed to the first source they opened an edition from, anonymous readers
+/// can't be attributed. The read time is stored with the sources, so both survive the retention
+/// policy.
+#[cfg(feature = "server")]
+pub async fn sources_per_edition() -> Result<BTreeMap<EditionId, Vec<SourceStats>>, ServerFnError> {
+    type Source = (
+        String,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+    );
+
+    /// A read source with its client and edition id
+    type ClientSource = (
+        Uuid,
+        EditionId,
+        String,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+    );
+
+    let db = db();
+
+    let sources: Vec<ClientSource> = read_sources::Entity::find()
+        .select_only()
+        .column(read_sources::Column::ClientId)
+        .column(read_sources::Column::EditionId)
+        .column(read_sources::Column::Entry)
+        .column(read_sources::Column::Referrer)
+        .column(read_sources::Column::UtmSource)
+        .column(read_sources::Column::UtmMedium)
+        .column(read_sources::Column::UtmCampaign)
+        .filter(read_sources::Column::ClientId.ne(NO_ID))
+        .order_by_asc(read_sources::Column::Timestamp)
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to get read sources: {err}")))?;
+
+    // sources are sorted by timestamp, so the first one of a reader is kept
+    let mut first_sources = HashMap::<(Uuid, EditionId), Source>::new();
+    for (client_id, edition_id, entry, referrer, utm_source, utm_medium, utm_campaign) in sources {
+        first_sources.entry((client_id, edition_id)).or_insert((
+            entry,
+            referrer,
+            utm_source,
+            utm_medium,
+            utm_campaign,
+        ));
+    }
+
+    // (client id, edition id, read time) of all sessions of a reader, whatever their source
+    let read_times: Vec<(Uuid, EditionId, f32)> = read_sources::Entity::find()
+        .select_only()
+        .column(read_sources::Column::ClientId)
+        .column(read_sources::Column::EditionId)
+        .expr(read_sources::Column::ReadTime.sum())
+        .filter(read_sources::Column::ClientId.ne(NO_ID))
+        .group_by(read_sources::Column::ClientId)
+        .group_by(read_sources::Column::EditionId)
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to get read times per reader: {err}")))?;
+    let read_times = read_times
+        .into_iter()
+        .map(|(client_id, edition_id, read_time)| ((client_id, edition_id), read_time))
+        .collect::<HashMap<_, _>>();
+
+    let mut per_source = BTreeMap::<(EditionId, Source), (HashSet<Uuid>, f32)>::new();
+    for ((client_id, edition_id), source) in first_sources {
+        let stats = per_source.entry((edition_id, source)).or_default();
+        stats.0.insert(client_id);
+        stats.1 += read_times
+            .get(&(client_id, edition_id))
+            .copied()
+            .unwrap_or_default();
+    }
+
+    let mut per_edition = BTreeMap::<EditionId, Vec<SourceStats>>::new();
+    for ((edition_id, (entry, referrer, source, medium, campaign)), (readers, read_time)) in
+        per_source
+    {
+        per_edition
+            .entry(edition_id)
+            .or_default()
+            .push(SourceStats {
+                entry,
+                referrer,
+                campaign: Campaign {
+                    source,
+                    medium,
+                    campaign,
+                },
+                readers: readers.len() as i64,
+                read_time,
+            });
+    }
+    Ok(per_edition)
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    const EDITION: Route = Route::Edition { id: 1 };
+
+    #[test]
+    fn direct_load_is_direct() {
+        let mut history = RouteHistory::default();
+        history.visit(EDITION);
+        assert_eq!(history.entry_point(&EDITION), EntryPoint::Direct);
+    }
+
+    #[test]
+    fn direct_load_is_direct_before_the_navbar_visits() {
+        assert_eq!(
+            RouteHistory::default().entry_point(&EDITION),
+            EntryPoint::Direct
+        );
+    }
+
+    #[test]
+    fn navigation_from_archive_is_archive() {
+        let mut history = RouteHistory::default();
+        history.visit(Route::Archiv {});
+        // the navbar's effect runs first
+        history.visit(EDITION);
+        assert_eq!(history.entry_point(&EDITION), EntryPoint::Archive);
+
+        let mut history = RouteHistory::default();
+        history.visit(Route::Archiv {});
+        assert_eq!(history.entry_point(&EDITION), EntryPoint::Archive);
+    }
+
+    #[test]
+    fn navigation_from_other_pages_is_internal() {
+        let mut history = RouteHistory::default();
+        history.visit(Route::About {});
+        history.visit(EDITION);
+        assert_eq!(history.entry_point(&EDITION), EntryPoint::Internal);
+    }
+
+    #[test]
+    fn home_is_home() {
+        let mut history = RouteHistory::default();
+        history.visit(Route::Home {});
+        assert_eq!(history.entry_point(&Route::Home {}), EntryPoint::Home);
+    }
+}
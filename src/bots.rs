@@ -0,0 +1,214 @@
+//! Detect bot and crawler traffic, which is stored in `flagged_traffic` instead of being counted.
+//! Flagged traffic is aggregated per day, kind, edition, client id, User-Agent and reason.
+
+use crate::{
+    components::EditionId,
+    db::{db, entities::flagged_traffic},
+    track_views::NO_ID,
+};
+use dioxus::{fullstack::FullstackContext, prelude::*, server::http::header};
+use sea_orm::{
+    ColumnTrait, EntityTrait, QueryFilter, QuerySelect, Set,
+    prelude::Expr,
+    sea_query::{Alias, OnConflict},
+};
+use time::UtcDateTime;
+use uuid::Uuid;
+
+/// Case-insensitive User-Agent fragments of crawlers, link previews and scripted clients
+const BOT_USER_AGENTS: &[&str] = &[
+    "bot",
+    "crawl",
+    "spider",
+    "slurp",
+    "preview",
+    "facebookexternalhit",
+    "whatsapp",
+    "telegram",
+    "discord",
+    "slack",
+    "skype",
+    "embedly",
+    "lighthouse",
+    "headless",
+    "curl",
+    "wget",
+    "python",
+    "go-http-client",
+    "java/",
+    "okhttp",
+    "axios",
+    "node-fetch",
+];
+
+/// Stored User-Agents are truncated to this many characters
+const MAX_USER_AGENT_LENGTH: usize = 256;
+
+/// The longest plausible read time of a single flush in milliseconds.
+/// The client flushes every 5 seconds and doesn't count time while the page is hidden.
+const MAX_FLUSH_READ_TIME: f32 = 60_000.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    View,
+    Read,
+}
+impl Kind {
+    pub const fn to_str(self) -> &'static str {
+        match self {
+            Self::View => "view",
+            Self::Read => "read",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reason {
+    UserAgent,
+    MissingClientId,
+    /// The read times of a flush are implausible for a human reader
+    Behaviour,
+}
+impl Reason {
+    pub const fn to_str(self) -> &'static str {
+        match self {
+            Self::UserAgent => "user-agent",
+            Self::MissingClientId => "missing-client-id",
+            Self::Behaviour => "behaviour",
+        }
+    }
+}
+
+/// The User-Agent of the current request
+pub fn user_agent() -> Option<String> {
+    FullstackContext::current().and_then(|context| {
+        context
+            .parts_mut()
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|user_agent| user_agent.to_str().ok())
+            .map(str::to_string)
+    })
+}
+
+/// Missing and empty User-Agents are treated as bots, as all browsers send one
+pub fn is_bot_user_agent(user_agent: Option<&str>) -> bool {
+    let Some(user_agent) = user_agent.map(str::to_lowercase) else {
+        return true;
+    };
+
+    user_agent.trim().is_empty()
+        || BOT_USER_AGENTS
+            .iter()
+            .any(|fragment| user_agent.contains(fragment))
+}
+
+/// Check a view for bot traffic. `client_id` is None for anonymous readers without consent.
+/// Browsers of consenting readers always send the client id cookie, as it is set when consenting.
+pub fn classify_view(user_agent: Option<&str>, client_id: Option<Uuid>) -> Option<Reason> {
+    if is_bot_user_agent(user_agent) {
+        return Some(Reason::UserAgent);
+    }
+    (client_id == Some(NO_ID)).then_some(Reason::MissingClientId)
+}
+
+/// Check a read time flush for bot traffic
+pub fn classify_read(
+    user_agent: Option<&str>,
+    client_id: Option<Uuid>,
+    page_times: &[f32],
+) -> Option<Reason> {
+    if let Some(reason) = classify_view(user_agent, client_id) {
+        return Some(reason);
+    }
+
+    let total = page_times.iter().sum::<f32>();
+
+    (!total.is_finite() || total > MAX_FLUSH_READ_TIME || page_times.iter().any(|time| *time < 0.0))
+        .then_some(Reason::Behaviour)
+}
+
+/// Store flagged traffic instead of counting it, adding it to today's row of the same traffic.
+/// Missing User-Agents are stored as empty, so they are aggregated too.
+pub async fn flag_traffic(
+    kind: Kind,
+    edition_id: EditionId,
+    client_id: Uuid,
+    user_agent: Option<String>,
+    reason: Reason,
+    read_time: f32,
+) -> Result<(), ServerFnError> {
+    let flagged = flagged_traffic::ActiveModel {
+        kind: Set(kind.to_str().to_string()),
+        edition_id: Set(edition_id),
+        client_id: Set(client_id),
+        user_agent: Set(Some(
+            user_agent
+                .unwrap_or_default()
+                .chars()
+                .take(MAX_USER_AGENT_LENGTH)
+                .collect(),
+        )),
+        reason: Set(reason.to_str().to_string()),
+        // non-finite values can't be stored meaningfully
+        read_time: Set(if read_time.is_finite() {
+            read_time
+        } else {
+            0.0
+        }),
+        day: Set(UtcDateTime::now().date()),
+        occurrences: Set(1),
+        ..Default::default()
+    };
+
+    flagged_traffic::Entity::insert(flagged)
+        .on_conflict(
+            OnConflict::columns([
+                flagged_traffic::Column::Kind,
+                flagged_traffic::Column::EditionId,
+                flagged_traffic::Column::ClientId,
+                flagged_traffic::Column::UserAgent,
+                flagged_traffic::Column::Reason,
+                flagged_traffic::Column::Day,
+            ])
+            .value(
+                flagged_traffic::Column::Occurrences,
+                Expr::col((
+                    flagged_traffic::Entity,
+                    flagged_traffic::Column::Occurrences,
+                ))
+                .add(1),
+            )
+            .value(
+                flagged_traffic::Column::ReadTime,
+                Expr::col((flagged_traffic::Entity, flagged_traffic::Column::ReadTime)).add(
+                    Expr::col((Alias::new("excluded"), flagged_traffic::Column::ReadTime)),
+                ),
+            )
+            .to_owned(),
+        )
+        .exec(db())
+        .await
+        .map(|_| ())
+        .map_err(|err| ServerFnError::new(format!("Failed to store flagged traffic: {err}")))
+}
+
+/// (edition id, flagged count, flagged read time) for every edition with flagged traffic of a kind
+pub async fn flagged_per_edition(kind: Kind) -> Result<Vec<(EditionId, i64, f64)>, ServerFnError> {
+    flagged_traffic::Entity::find()
+        .select_only()
+        .column(flagged_traffic::Column::EditionId)
+        .expr(flagged_traffic::Column::Occurrences.sum())
+        .expr(flagged_traffic::Column::ReadTime.sum())
+        .filter(flagged_traffic::Column::Kind.eq(kind.to_str()))
+        .group_by(flagged_traffic::Column::EditionId)
+        .into_tuple()
+        .all(db())
+        .await
+        .map_err(|err| {
+            ServerFnError::new(format!(
+                "Failed to get flagged {} counts: {err}",
+                kind.to_str()
+            ))
+        })
+}
@@ -0,0 +1,144 @@
+//! Minimal server-side SVG charts, so the admin views work without any JS chart library
+
+use std::fmt::Write;
+
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 240.0;
+const MARGIN_LEFT: f64 = 48.0;
+const MARGIN_BOTTOM: f64 = 32.0;
+const MARGIN_TOP: f64 = 24.0;
+
+const BAR_COLOR: &str = "#91a4d2";
+const TEXT_COLOR: &str = "#ffffff";
+const AXIS_COLOR: &str = "#6b7280";
+
+/// Escape a string for use in svg text and attributes
+fn xml_escape(string: &str) -> String {
+    string
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Round the maximum of a chart up, so the y axis doesn't end on an arbitrary value
+fn nice_max(max: f64) -> f64 {
+    if max <= 0.0 {
+        return 1.0;
+    }
+    let magnitude = 10f64.powf(max.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|step| step * magnitude)
+        .find(|candidate| *candidate >= max)
+        .unwrap_or(10.0 * magnitude)
+}
+
+fn open_svg(title: &str) -> String {
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {WIDTH} {HEIGHT}\" width=\"100%\" role=\"img\">"
+    );
+    let _ = write!(
+        svg,
+        "<title>{title}</title><text x=\"{x}\" y=\"16\" fill=\"{TEXT_COLOR}\" font-size=\"14\" text-anchor=\"middle\">{title}</text>",
+        title = xml_escape(title),
+        x = WIDTH / 2.0,
+    );
+    svg
+}
+
+/// Draw the axes and a label for the maximum y value
+fn axes(svg: &mut String, y_max: f64) {
+    let bottom = HEIGHT - MARGIN_BOTTOM;
+    let _ = write!(
+        svg,
+        "<line x1=\"{MARGIN_LEFT}\" y1=\"{MARGIN_TOP}\" x2=\"{MARGIN_LEFT}\" y2=\"{bottom}\" stroke=\"{AXIS_COLOR}\"/>\
+         <line x1=\"{MARGIN_LEFT}\" y1=\"{bottom}\" x2=\"{WIDTH}\" y2=\"{bottom}\" stroke=\"{AXIS_COLOR}\"/>\
+         <text x=\"{x}\" y=\"{y}\" fill=\"{TEXT_COLOR}\" font-size=\"10\" text-anchor=\"end\">{y_max}</text>\
+         <text x=\"{x}\" y=\"{bottom}\" fill=\"{TEXT_COLOR}\" font-size=\"10\" text-anchor=\"end\">0</text>",
+        x = MARGIN_LEFT - 4.0,
+        y = MARGIN_TOP + 4.0,
+    );
+}
+
+/// Render a bar chart of (label, value) pairs
+pub fn bar_chart(title: &str, bars: &[(String, f64)]) -> String {
+    let mut svg = open_svg(title);
+
+    let y_max = nice_max(bars.iter().map(|(_, value)| *value).fold(0.0, f64::max));
+    axes(&mut svg, y_max);
+
+    let plot_width = WIDTH - MARGIN_LEFT;
+    let plot_height = HEIGHT - MARGIN_BOTTOM - MARGIN_TOP;
+    let slot = plot_width / bars.len().max(1) as f64;
+
+    for (i, (label, value)) in bars.iter().enumerate() {
+        let height = plot_height * value / y_max;
+        let x = MARGIN_LEFT + slot * i as f64;
+        let _ = write!(
+            svg,
+            "<rect x=\"{x}\" y=\"{y}\" width=\"{width}\" height=\"{height}\" fill=\"{BAR_COLOR}\"><title>{label}: {value:.1}</title></rect>\
+             <text x=\"{label_x}\" y=\"{label_y}\" fill=\"{TEXT_COLOR}\" font-size=\"10\" text-anchor=\"middle\">{label}</text>",
+            x = x + slot * 0.1,
+            y = HEIGHT - MARGIN_BOTTOM - height,
+            width = slot * 0.8,
+            label = xml_escape(label),
+            label_x = x + slot / 2.0,
+            label_y = HEIGHT - MARGIN_BOTTOM + 14.0,
+        );
+    }
+
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Render a line chart of (x, y) points. The points are expected to be sorted by x.
+pub fn line_chart(title: &str, x_label: &str, points: &[(f64, f64)]) -> String {
+    let mut svg = open_svg(title);
+
+    let y_max = nice_max(points.iter().map(|(_, y)| *y).fold(0.0, f64::max));
+    let x_max = points.iter().map(|(x, _)| *x).fold(0.0, f64::max).max(1.0);
+    axes(&mut svg, y_max);
+
+    let plot_width = WIDTH - MARGIN_LEFT - 8.0;
+    let plot_height = HEIGHT - MARGIN_BOTTOM - MARGIN_TOP;
+    let to_svg = |(x, y): (f64, f64)| {
+        (
+            MARGIN_LEFT + plot_width * x / x_max,
+            HEIGHT - MARGIN_BOTTOM - plot_height * y / y_max,
+        )
+    };
+
+    let path = points
+        .iter()
+        .map(|point| {
+            let (x, y) = to_svg(*point);
+            format!("{x:.1},{y:.1}")
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    let _ = write!(
+        svg,
+        "<polyline points=\"{path}\" fill=\"none\" stroke=\"{BAR_COLOR}\" stroke-width=\"2\"/>"
+    );
+    for point in points {
+        let (x, y) = to_svg(*point);
+        let _ = write!(
+            svg,
+            "<circle cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"3\" fill=\"{BAR_COLOR}\"><title>{}: {}</title></circle>",
+            point.0, point.1
+        );
+    }
+
+    let _ = write!(
+        svg,
+        "<text x=\"{x}\" y=\"{y}\" fill=\"{TEXT_COLOR}\" font-size=\"10\" text-anchor=\"end\">{x_max}</text>\
+         <text x=\"{center}\" y=\"{y}\" fill=\"{TEXT_COLOR}\" font-size=\"10\" text-anchor=\"middle\">{x_label}</text></svg>",
+        x = WIDTH - 8.0,
+        y = HEIGHT - MARGIN_BOTTOM + 14.0,
+        center = MARGIN_LEFT + plot_width / 2.0,
+        x_label = xml_escape(x_label),
+    );
+    svg
+}
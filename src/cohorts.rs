@@ -0,0 +1,155 @@
+//! New vs returning readers: which edition each client was first seen on, how many read several
+//! editions and how many readers of an edition come back for the next one.
+//! Only reads with a client id count, anonymous reads can't be followed.
+
+use crate::db::entities::edition;
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use crate::{
+    components::{EditionId, require_admin},
+    db::{db, entities::first_reads},
+};
+#[cfg(feature = "server")]
+use sea_orm::{ColumnTrait, EntityTrait, QuerySelect};
+#[cfg(feature = "server")]
+use std::collections::{BTreeMap, HashMap, HashSet};
+#[cfg(feature = "server")]
+use time::Date;
+#[cfg(feature = "server")]
+use uuid::Uuid;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EditionCohort {
+    pub edition: edition::Model,
+    pub readers: i64,
+    /// Readers whose first read was of this edition
+    pub new_readers: i64,
+    /// Readers who read another edition before this one
+    pub returning_readers: i64,
+    /// Readers of the previous edition who also read this one. None for the first edition.
+    pub retained_from_previous: Option<i64>,
+    /// `retained_from_previous` relative to the readers of the previous edition
+    pub retention_rate: Option<f64>,
+    /// New readers of this edition who went on to read any other edition
+    pub cohort_retained: i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CohortReport {
+    /// Ordered by date
+    pub editions: Vec<EditionCohort>,
+    /// (editions read, readers who read that many editions), ordered by editions read
+    pub editions_per_reader: Vec<(usize, i64)>,
+    /// Readers who read more than one edition
+    pub multi_edition_readers: i64,
+    pub readers: i64,
+}
+
+/// Compute the cohorts from the editions and the first read of each client and edition.
+/// Readers are new on the edition they read first, regardless of the edition dates, so readers
+/// that start with an older edition from the archive are new there.
+/// The first reads are taken from `first_reads`, which survives the retention policy but only
+/// covers rolled up reads. Editions first read on the same day are ordered by their date.
+#[cfg(feature = "server")]
+pub async fn cohort_report() -> Result<CohortReport, ServerFnError> {
+    let mut editions = edition::Entity::find()
+        .all(db())
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to get editions: {err}")))?;
+    editions.sort_by_key(|edition| edition.date);
+
+    let reads: Vec<(Uuid, EditionId, Date)> = first_reads::Entity::find()
+        .select_only()
+        .column(first_reads::Column::ClientId)
+        .column(first_reads::Column::EditionId)
+        .expr(first_reads::Column::Day.min())
+        .group_by(first_reads::Column::ClientId)
+        .group_by(first_reads::Column::EditionId)
+        .into_tuple()
+        .all(db())
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to get readers per edition: {err}")))?;
+
+    let index = editions
+        .iter()
+        .enumerate()
+        .map(|(i, edition)| (edition.id, i))
+        .collect::<HashMap<_, _>>();
+
+    let mut readers = vec![HashSet::<Uuid>::new(); editions.len()];
+    // client id -> (first read, index) of the read editions
+    let mut first_reads = HashMap::<Uuid, Vec<(Date, usize)>>::new();
+    for (client_id, edition_id, first_read) in reads {
+        let Some(&i) = index.get(&edition_id) else {
+            continue;
+        };
+        readers[i].insert(client_id);
+        first_reads
+            .entry(client_id)
+            .or_default()
+            .push((first_read, i));
+    }
+    // client id -> indices of the read editions, in the order they were first read
+    let read_editions = first_reads
+        .into_iter()
+        .map(|(client_id, mut first_reads)| {
+            first_reads.sort_unstable();
+            let indices = first_reads.into_iter().map(|(_, i)| i).collect::<Vec<_>>();
+            (client_id, indices)
+        })
+        .collect::<HashMap<_, _>>();
+
+    let mut new_readers = vec![0; editions.len()];
+    let mut cohort_retained = vec![0; editions.len()];
+    let mut editions_per_reader = BTreeMap::<usize, i64>::new();
+    for indices in read_editions.values() {
+        new_readers[indices[0]] += 1;
+        if indices.len() > 1 {
+            cohort_retained[indices[0]] += 1;
+        }
+        *editions_per_reader.entry(indices.len()).or_default() += 1;
+    }
+
+    let editions = editions
+        .into_iter()
+        .enumerate()
+        .map(|(i, edition)| {
+            let retained_from_previous = i
+                .checked_sub(1)
+                .map(|previous| readers[previous].intersection(&readers[i]).count() as i64);
+            let retention_rate = retained_from_previous
+                .zip(i.checked_sub(1))
+                .filter(|(_, previous)| !readers[*previous].is_empty())
+                .map(|(retained, previous)| retained as f64 / readers[previous].len() as f64);
+
+            EditionCohort {
+                edition,
+                readers: readers[i].len() as i64,
+                new_readers: new_readers[i],
+                returning_readers: readers[i].len() as i64 - new_readers[i],
+                retained_from_previous,
+                retention_rate,
+                cohort_retained: cohort_retained[i],
+            }
+        })
+        .collect();
+
+    Ok(CohortReport {
+        editions,
+        multi_edition_readers: editions_per_reader
+            .range(2..)
+            .map(|(_, readers)| readers)
+            .sum(),
+        editions_per_reader: editions_per_reader.into_iter().collect(),
+        readers: read_editions.len() as i64,
+    })
+}
+
+#[server]
+#[tracing::instrument(skip_all, err)]
+pub async fn fetch_cohorts() -> Result<CohortReport, ServerFnError> {
+    require_admin().await?;
+    cohort_report().await
+}
@@ -0,0 +1,63 @@
+use crate::{cookies::set_cookie, i18n};
+use dioxus::prelude::*;
+
+const STORAGE_KEY: &str = "admin_token";
+
+/// Whether `token` is the configured admin token. Always false without one.
+#[cfg(feature = "server")]
+pub fn is_admin_token(token: &str) -> bool {
+    crate::config::config()
+        .admin_token
+        .as_deref()
+        .is_some_and(|expected| token.trim() == expected)
+}
+
+/// Fail unless the request carries the admin token cookie
+#[cfg(feature = "server")]
+pub async fn require_admin() -> Result<(), ServerFnError> {
+    use crate::cookies::get_cookie;
+
+    match get_cookie(STORAGE_KEY, |token| Some(token.to_string())) {
+        Some(token) if is_admin_token(&token) => Ok(()),
+        _ => Err(ServerFnError::new("Not authorized")),
+    }
+}
+
+#[server]
+#[tracing::instrument(err)]
+pub async fn is_admin() -> Result<bool, ServerFnError> {
+    Ok(require_admin().await.is_ok())
+}
+
+/// Only render children if the admin token cookie is set, otherwise ask for it
+#[component]
+pub fn AdminOnly(children: Element) -> Element {
+    let mut authorized = use_server_future(move || async move { is_admin().await })?;
+    let mut token = use_signal(String::new);
+
+    let lang = i18n::use_lang();
+
+    if let Some(Ok(true)) = &*authorized.read_unchecked() {
+        return children;
+    }
+
+    rsx! {
+        form {
+            onsubmit: move |evt: FormEvent| {
+                evt.prevent_default();
+                set_cookie(STORAGE_KEY, &token());
+                authorized.restart();
+            },
+            label { "{lang.read().admin_token()}" }
+            br {}
+            input {
+                r#type: "password",
+                style: "color: black;",
+                value: "{token}",
+                oninput: move |evt| token.set(evt.value()),
+            }
+            br {}
+            button { "{lang.read().log_in()}" }
+        }
+    }
+}
@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 #[cfg(feature = "server")]
 use crate::db::db;
 #[cfg(feature = "server")]
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, sea_query::Expr};
+use sea_orm::EntityTrait;
 
 use crate::db::entities::edition;
 
@@ -17,6 +17,7 @@ pub type EditionId = i32;
 // When the server function is called from the client, it will just serialize the arguments, call the API, and deserialize the
 // response.
 #[server]
+#[tracing::instrument(err)]
 pub async fn fetch_editions() -> Result<Vec<edition::Model>, ServerFnError> {
     edition::Entity::find()
         .all(db())
@@ -25,32 +26,29 @@ pub async fn fetch_editions() -> Result<Vec<edition::Model>, ServerFnError> {
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct ViewEdition {
+pub struct EditionData {
     pub edition: edition::Model,
     pub num_pages: u8,
 }
 
+/// Fetch an edition and its page count. Views are recorded separately by `record_view`.
 #[server]
-pub async fn view_edition(id: i32) -> Result<ViewEdition, ServerFnError> {
-    let db = db();
-
-    // increment view count
-    let _ = edition::Entity::update_many()
-        .col_expr(
-            edition::Column::Views,
-            Expr::col(edition::Column::Views).add(1),
-        )
-        .filter(edition::Column::Id.eq(id))
-        .exec(db)
-        .await; // ignore error, TODO: log instead
-
-    // get updated entity
+#[tracing::instrument(err)]
+pub async fn fetch_edition(id: i32) -> Result<EditionData, ServerFnError> {
     let edition = edition::Entity::find_by_id(id)
-        .one(db)
+        .one(db())
         .await
         .map_err(|err| ServerFnError::new(err.to_string()))?
         .ok_or(ServerFnError::new(format!("Edition {id} not found")))?;
 
+    let num_pages = count_pages(&edition).await?;
+
+    Ok(EditionData { edition, num_pages })
+}
+
+/// Count the rendered svg pages of an edition
+#[cfg(feature = "server")]
+pub async fn count_pages(edition: &edition::Model) -> Result<u8, ServerFnError> {
     let mut read_dir = tokio::fs::read_dir(&format!("svgs/{}", edition.date))
         .await
         .map_err(|err| {
@@ -65,5 +63,5 @@ pub async fn view_edition(id: i32) -> Result<ViewEdition, ServerFnError> {
         num_pages += 1;
     }
 
-    Ok(ViewEdition { edition, num_pages })
+    Ok(num_pages)
 }
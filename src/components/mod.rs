@@ -3,4 +3,11 @@
 //! component and an Echo component for fullstack apps to be used in our app.
 
 mod editions;
-pub use editions::{EditionId, ViewEdition, fetch_editions, view_edition};
+#[cfg(feature = "server")]
+pub use editions::count_pages;
+pub use editions::{EditionData, EditionId, fetch_edition, fetch_editions};
+
+mod admin;
+pub use admin::AdminOnly;
+#[cfg(feature = "server")]
+pub use admin::{is_admin_token, require_admin};
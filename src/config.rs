@@ -0,0 +1,430 @@
+//! Server configuration, loaded once at startup.
+//!
+//! Read from the TOML file at `DIEPROBEZEIT_CONFIG` (default `dieprobezeit.toml`, which may be
+//! missing), then overridden by the environment:
+//! - `DIEPROBEZEIT_DATABASE_URL`, `DIEPROBEZEIT_SITE_URL`, `DIEPROBEZEIT_LOG_FORMAT`
+//! - `DIEPROBEZEIT_ADMIN_TOKEN`. If set neither way, it is read from the file `admin/token`, where
+//!   it was kept before.
+//! - `DIEPROBEZEIT_ROLLUP_INTERVAL` in seconds
+//! - `DIEPROBEZEIT_EXPORT_BACKEND`, `DIEPROBEZEIT_EXPORT_FORMATS` (comma separated),
+//!   `DIEPROBEZEIT_EXPORT_DIR`, `DIEPROBEZEIT_EXPORT_INTERVAL` and
+//!   `DIEPROBEZEIT_EXPORT_INTERVAL_<SUBJECT>` in seconds
+//! - `DIEPROBEZEIT_KDRIVE_DRIVE_ID`, `DIEPROBEZEIT_KDRIVE_OAUTH_TOKEN`,
+//!   `DIEPROBEZEIT_KDRIVE_DIRECTORY_ID`, `DIEPROBEZEIT_KDRIVE_FILE_ID`. kDrive values set neither
+//!   way are read from the files `kdrive/drive-id`, `kdrive/oauth-token`, `kdrive/directory-id` and
+//!   `kdrive/file-id`, where they were kept before.
+//! - `DIEPROBEZEIT_WEBDAV_URL`, `DIEPROBEZEIT_WEBDAV_USER`, `DIEPROBEZEIT_WEBDAV_PASSWORD`
+//! - `DIEPROBEZEIT_READ_RETENTION_DAYS`, `DIEPROBEZEIT_READ_RETENTION_MODE`,
+//!   `DIEPROBEZEIT_READ_RETENTION_DRY_RUN`
+//!
+//! See `dieprobezeit.example.toml` for the file format.
+//!
+//! Invalid export settings don't stop the site, they only disable the exports and the team download
+//! (see `ExportConfig::validate`).
+
+use crate::{export::Format, logging::LogFormat, retention::RetentionPolicy};
+use dioxus::fullstack::reqwest::Url;
+use serde::{Deserialize, de::IntoDeserializer};
+use std::{
+    collections::BTreeMap, env, fmt::Display, io::ErrorKind, path::PathBuf, str::FromStr,
+    sync::OnceLock, time::Duration,
+};
+
+const DEFAULT_PATH: &str = "dieprobezeit.toml";
+/// Directory of the kDrive files used before the config file
+const LEGACY_KDRIVE_DIR: &str = "kdrive";
+/// File of the admin token used before the config file
+const LEGACY_ADMIN_TOKEN_FILE: &str = "admin/token";
+/// Shortest accepted admin token
+const MIN_ADMIN_TOKEN_LENGTH: usize = 16;
+
+/// Everything exported on an interval, and the team download
+pub const SUBJECTS: &[&str] = &[
+    "editions",
+    "feedback",
+    "reads",
+    "pages",
+    "daily_reads",
+    "completion",
+    "sources",
+    "sessions",
+    "cohorts",
+    "team",
+];
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// sea-orm connection url
+    pub database_url: String,
+    /// Public url of the site, without a trailing slash. Links in the feed point to it.
+    pub site_url: String,
+    pub log_format: LogFormat,
+    /// Token of the admin pages and `/metrics`. They are inaccessible if missing.
+    pub admin_token: Option<String>,
+    /// Seconds between rollups of the reads
+    pub rollup_interval: u64,
+    pub export: ExportConfig,
+    /// Not applied if missing
+    pub retention: Option<RetentionPolicy>,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            database_url: "sqlite://mng.db?mode=rwc".to_string(),
+            site_url: "https://dieprobezeit.ch".to_string(),
+            log_format: LogFormat::Pretty,
+            admin_token: None,
+            rollup_interval: 5 * 60,
+            export: ExportConfig::default(),
+            retention: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    #[default]
+    KDrive,
+    Local,
+    WebDav,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExportConfig {
+    pub backend: BackendKind,
+    /// Every export is uploaded in each of these formats
+    pub formats: Vec<Format>,
+    /// Directory of the local backend
+    pub dir: PathBuf,
+    pub kdrive: KDriveConfig,
+    pub webdav: WebDavConfig,
+    /// Seconds between exports
+    pub interval: u64,
+    /// Seconds between exports of single subjects, overriding `interval`
+    pub intervals: BTreeMap<String, u64>,
+}
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            backend: BackendKind::default(),
+            formats: vec![Format::Csv, Format::Json, Format::Xlsx],
+            dir: "exports".into(),
+            kdrive: KDriveConfig::default(),
+            webdav: WebDavConfig::default(),
+            interval: 60,
+            intervals: BTreeMap::new(),
+        }
+    }
+}
+impl ExportConfig {
+    /// Check the settings the exports and the team download need. Errors only disable them, the
+    /// rest of the site keeps running.
+    pub fn validate(&self) -> Result<(), String> {
+        let invalid = |message: String| Err(message);
+
+        if self.formats.is_empty() {
+            return invalid("export.formats must not be empty".to_string());
+        }
+        if self.interval == 0 {
+            return invalid("export.interval must be a positive number of seconds".to_string());
+        }
+        for (subject, interval) in &self.intervals {
+            if !SUBJECTS.contains(&subject.as_str()) {
+                return invalid(format!(
+                    "Unknown subject {subject:?} in export.intervals, expected one of {SUBJECTS:?}"
+                ));
+            }
+            if *interval == 0 {
+                return invalid(format!(
+                    "export.intervals.{subject} must be a positive number of seconds"
+                ));
+            }
+        }
+
+        match self.backend {
+            BackendKind::KDrive => {
+                let kdrive = &self.kdrive;
+                let missing = [
+                    ("drive_id", &kdrive.drive_id),
+                    ("oauth_token", &kdrive.oauth_token),
+                    ("directory_id", &kdrive.directory_id),
+                    ("file_id", &kdrive.file_id),
+                ]
+                .into_iter()
+                .filter(|(_, value)| value.as_deref().is_none_or(|value| value.trim().is_empty()))
+                .map(|(name, _)| format!("export.kdrive.{name}"))
+                .collect::<Vec<_>>();
+                if !missing.is_empty() {
+                    return invalid(format!(
+                        "The kdrive export backend requires {}, or choose another export.backend",
+                        missing.join(", ")
+                    ));
+                }
+            }
+            BackendKind::Local => {}
+            BackendKind::WebDav => match &self.webdav.url {
+                None => {
+                    return invalid(
+                        "The webdav export backend requires export.webdav.url".to_string(),
+                    );
+                }
+                Some(url) => {
+                    if let Err(err) = Url::parse(url) {
+                        return invalid(format!("Invalid export.webdav.url {url:?}: {err}"));
+                    }
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    /// How often to export the subject (one of `SUBJECTS`)
+    pub fn interval_of(&self, subject: &str) -> Duration {
+        Duration::from_secs(*self.intervals.get(subject).unwrap_or(&self.interval))
+    }
+}
+
+/// Required with the kDrive backend
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KDriveConfig {
+    pub drive_id: Option<String>,
+    pub oauth_token: Option<String>,
+    /// Exports are uploaded into this directory
+    pub directory_id: Option<String>,
+    /// The team file
+    pub file_id: Option<String>,
+}
+impl KDriveConfig {
+    /// Read the values that are still unset from the legacy kDrive files, if they exist
+    fn apply_legacy_files(&mut self) {
+        for (file_name, value) in [
+            ("drive-id", &mut self.drive_id),
+            ("oauth-token", &mut self.oauth_token),
+            ("directory-id", &mut self.directory_id),
+            ("file-id", &mut self.file_id),
+        ] {
+            if value
+                .as_deref()
+                .is_some_and(|value| !value.trim().is_empty())
+            {
+                continue;
+            }
+            let path = PathBuf::from(LEGACY_KDRIVE_DIR).join(file_name);
+            if let Ok(content) = std::fs::read_to_string(path) {
+                *value = Some(content.trim().to_string());
+            }
+        }
+    }
+}
+
+/// `url` is required with the WebDAV backend, the credentials are optional
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebDavConfig {
+    pub url: Option<String>,
+    pub user: Option<String>,
+    pub password: Option<String>,
+}
+
+impl Config {
+    /// Read the config file, apply the environment overrides and the legacy kDrive and admin token
+    /// files, and validate the result. The export settings are validated separately.
+    pub fn load() -> Result<Self, String> {
+        let explicit_path = env::var("DIEPROBEZEIT_CONFIG").ok();
+        let path = explicit_path.as_deref().unwrap_or(DEFAULT_PATH);
+
+        let mut config = match std::fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|err| format!("Invalid config file {path}: {err}"))?,
+            // the default file is optional, an explicitly given one isn't. Docker bind mounts a
+            // missing file as an empty directory.
+            Err(err)
+                if matches!(err.kind(), ErrorKind::NotFound | ErrorKind::IsADirectory)
+                    && explicit_path.is_none() =>
+            {
+                Self::default()
+            }
+            Err(err) => {
+                return Err(format!("Failed to read config file {path}: {err}"));
+            }
+        };
+
+        config.apply_env()?;
+        config.export.kdrive.apply_legacy_files();
+        if config.admin_token.is_none()
+            && let Ok(token) = std::fs::read_to_string(LEGACY_ADMIN_TOKEN_FILE)
+        {
+            config.admin_token = Some(token);
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn apply_env(&mut self) -> Result<(), String> {
+        env_override("DIEPROBEZEIT_DATABASE_URL", &mut self.database_url, parse)?;
+        env_override("DIEPROBEZEIT_SITE_URL", &mut self.site_url, parse)?;
+        env_override("DIEPROBEZEIT_LOG_FORMAT", &mut self.log_format, variant)?;
+        env_override("DIEPROBEZEIT_ADMIN_TOKEN", &mut self.admin_token, |value| {
+            Ok(Some(value.to_string()))
+        })?;
+        env_override(
+            "DIEPROBEZEIT_ROLLUP_INTERVAL",
+            &mut self.rollup_interval,
+            parse,
+        )?;
+
+        let export = &mut self.export;
+        env_override("DIEPROBEZEIT_EXPORT_BACKEND", &mut export.backend, variant)?;
+        env_override(
+            "DIEPROBEZEIT_EXPORT_FORMATS",
+            &mut export.formats,
+            |value| {
+                value
+                    .split(',')
+                    .map(|format| variant(format.trim()))
+                    .collect()
+            },
+        )?;
+        env_override("DIEPROBEZEIT_EXPORT_DIR", &mut export.dir, parse)?;
+        env_override("DIEPROBEZEIT_EXPORT_INTERVAL", &mut export.interval, parse)?;
+        for subject in SUBJECTS {
+            let name = format!("DIEPROBEZEIT_EXPORT_INTERVAL_{}", subject.to_uppercase());
+            let mut interval = None;
+            env_override(&name, &mut interval, |value| parse(value).map(Some))?;
+            if let Some(interval) = interval {
+                export.intervals.insert(subject.to_string(), interval);
+            }
+        }
+
+        let kdrive = &mut export.kdrive;
+        let optional = |value: &str| Ok(Some(value.to_string()));
+        env_override(
+            "DIEPROBEZEIT_KDRIVE_DRIVE_ID",
+            &mut kdrive.drive_id,
+            optional,
+        )?;
+        env_override(
+            "DIEPROBEZEIT_KDRIVE_OAUTH_TOKEN",
+            &mut kdrive.oauth_token,
+            optional,
+        )?;
+        env_override(
+            "DIEPROBEZEIT_KDRIVE_DIRECTORY_ID",
+            &mut kdrive.directory_id,
+            optional,
+        )?;
+        env_override("DIEPROBEZEIT_KDRIVE_FILE_ID", &mut kdrive.file_id, optional)?;
+
+        let webdav = &mut export.webdav;
+        env_override("DIEPROBEZEIT_WEBDAV_URL", &mut webdav.url, optional)?;
+        env_override("DIEPROBEZEIT_WEBDAV_USER", &mut webdav.user, optional)?;
+        env_override(
+            "DIEPROBEZEIT_WEBDAV_PASSWORD",
+            &mut webdav.password,
+            optional,
+        )?;
+
+        // the days enable retention, the mode and dry run only change it
+        let mut days = None;
+        env_override("DIEPROBEZEIT_READ_RETENTION_DAYS", &mut days, |value| {
+            parse(value).map(Some)
+        })?;
+        if let Some(days) = days {
+            self.retention
+                .get_or_insert_with(|| RetentionPolicy::with_days(days))
+                .days = days;
+        }
+        if let Some(retention) = &mut self.retention {
+            env_override(
+                "DIEPROBEZEIT_READ_RETENTION_MODE",
+                &mut retention.mode,
+                variant,
+            )?;
+            env_override(
+                "DIEPROBEZEIT_READ_RETENTION_DRY_RUN",
+                &mut retention.dry_run,
+                parse,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn validate(&mut self) -> Result<(), String> {
+        let invalid = |message: String| Err(message);
+
+        if self.database_url.trim().is_empty() {
+            return invalid("database_url must not be empty".to_string());
+        }
+
+        if let Err(err) = Url::parse(&self.site_url) {
+            return invalid(format!("Invalid site_url {:?}: {err}", self.site_url));
+        }
+        self.site_url = self.site_url.trim_end_matches('/').to_string();
+
+        if self.rollup_interval == 0 {
+            return invalid("rollup_interval must be a positive number of seconds".to_string());
+        }
+
+        if let Some(token) = &mut self.admin_token {
+            *token = token.trim().to_string();
+            if token.chars().count() < MIN_ADMIN_TOKEN_LENGTH {
+                return invalid(format!(
+                    "admin_token must be at least {MIN_ADMIN_TOKEN_LENGTH} characters long"
+                ));
+            }
+            if token
+                .chars()
+                .any(|char| char.is_whitespace() || char.is_control())
+            {
+                return invalid("admin_token must not contain whitespace".to_string());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Replace `field` with the parsed value of the environment variable `name`, if it is set
+fn env_override<T>(
+    name: &str,
+    field: &mut T,
+    parse: impl FnOnce(&str) -> Result<T, String>,
+) -> Result<(), String> {
+    let Ok(value) = env::var(name) else {
+        return Ok(());
+    };
+
+    *field = parse(value.trim()).map_err(|err| format!("Invalid {name} {value:?}: {err}"))?;
+    Ok(())
+}
+
+/// Parse a number, bool, path or string
+fn parse<T: FromStr<Err: Display>>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|err: T::Err| err.to_string())
+}
+
+/// Parse an enum variant, named as in the config file
+fn variant<T: for<'de> Deserialize<'de>>(value: &str) -> Result<T, String> {
+    T::deserialize(value.into_deserializer())
+        .map_err(|err: serde::de::value::Error| err.to_string())
+}
+
+/// Make the config available through `config`. Panics if it is already set.
+pub fn init(config: Config) {
+    CONFIG
+        .set(config)
+        .unwrap_or_else(|_| panic!("Config already set"));
+}
+
+/// Panics if the config is unset
+pub fn config() -> &'static Config {
+    CONFIG.get().expect("Config not set")
+}
@@ -0,0 +1,157 @@
+//! Consent to tracking with a client id.
+//! Until a reader consents, or if their browser sends `DNT` or `Sec-GPC`, views and reads are
+//! recorded anonymously with `NO_ID` and no client id cookie is set.
+
+use crate::{
+    cookies::{get_cookie, set_cookie},
+    i18n,
+    track_views::{delete_client_id, ensure_client_id_set},
+    views::delete_stored_data,
+};
+use dioxus::prelude::*;
+
+const STORAGE_KEY: &str = "consent";
+const CONSENT_CSS: Asset = asset!("/assets/styling/consent.css");
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Consent {
+    Granted,
+    Denied,
+}
+impl Consent {
+    pub const fn to_str(self) -> &'static str {
+        match self {
+            Self::Granted => "granted",
+            Self::Denied => "denied",
+        }
+    }
+    pub fn from_str(str: &str) -> Option<Self> {
+        match str {
+            "granted" => Some(Self::Granted),
+            "denied" => Some(Self::Denied),
+            _ => None,
+        }
+    }
+}
+
+/// The reader's choice. None if they didn't decide yet.
+pub fn get_consent() -> Option<Consent> {
+    get_cookie(STORAGE_KEY, Consent::from_str)
+}
+
+/// Store consent and set the client id
+pub fn grant_consent() {
+    set_cookie(STORAGE_KEY, Consent::Granted.to_str());
+    ensure_client_id_set();
+}
+
+/// Store that the reader doesn't consent (anymore) and delete the client id
+pub fn withdraw_consent() {
+    set_cookie(STORAGE_KEY, Consent::Denied.to_str());
+    delete_client_id();
+}
+
+/// Whether the browser asks not to be tracked, with Do-Not-Track or Global Privacy Control
+pub fn privacy_signal() -> bool {
+    #[cfg(feature = "web")]
+    {
+        use web_sys::{js_sys::Reflect, wasm_bindgen::JsValue, window};
+
+        window().is_some_and(|window| {
+            let navigator = window.navigator();
+            navigator.do_not_track() == "1"
+                || Reflect::get(&navigator, &JsValue::from_str("globalPrivacyControl"))
+                    .is_ok_and(|gpc| gpc.is_truthy())
+        })
+    }
+    #[cfg(feature = "server")]
+    {
+        use dioxus::fullstack::FullstackContext;
+
+        FullstackContext::current().is_some_and(|context| {
+            let headers = &context.parts_mut().headers;
+            ["dnt", "sec-gpc"].iter().any(|header| {
+                headers
+                    .get(*header)
+                    .is_some_and(|value| value.as_bytes() == b"1")
+            })
+        })
+    }
+    #[cfg(not(any(feature = "web", feature = "server")))]
+    {
+        false
+    }
+}
+
+/// Whether views and reads may be tied to the client id
+pub fn tracking_allowed() -> bool {
+    get_consent() == Some(Consent::Granted) && !privacy_signal()
+}
+
+/// Asks for consent until the reader decides. Not shown if the browser sends a privacy signal.
+#[component]
+pub fn ConsentBanner() -> Element {
+    // only decided on the client, the server doesn't know about the navigator
+    let mut undecided = use_signal(|| false);
+    use_effect(move || undecided.set(get_consent().is_none() && !privacy_signal()));
+
+    let lang = i18n::use_lang();
+
+    rsx! {
+        document::Link { rel: "stylesheet", href: CONSENT_CSS }
+
+        if undecided() {
+            div { id: "consent-banner",
+                p { "{lang.read().consent_text()}" }
+                button {
+                    onclick: move |_| {
+                        grant_consent();
+                        undecided.set(false);
+                    },
+                    "{lang.read().consent_accept()}"
+                }
+                button {
+                    onclick: move |_| {
+                        withdraw_consent();
+                        undecided.set(false);
+                    },
+                    "{lang.read().consent_decline()}"
+                }
+            }
+        }
+    }
+}
+
+/// Lets a reader who consented withdraw it again. Their stored data is deleted first, as it can't
+/// be viewed or deleted anymore once the client id is gone.
+#[component]
+pub fn WithdrawConsent(ondeleted: EventHandler<()>) -> Element {
+    let mut granted = use_signal(|| false);
+    use_effect(move || granted.set(get_consent() == Some(Consent::Granted)));
+    let mut error = use_signal(|| None::<ServerFnError>);
+
+    let lang = i18n::use_lang();
+
+    rsx! {
+        if granted() {
+            button {
+                onclick: move |_| async move {
+                    match delete_stored_data().await {
+                        Ok(()) => {
+                            withdraw_consent();
+                            granted.set(false);
+                            ondeleted.call(());
+                        }
+                        Err(err) => error.set(Some(err)),
+                    }
+                },
+                "{lang.read().consent_withdraw()}"
+            }
+            if let Some(err) = &*error.read() {
+                p { "{lang.read().error_deleting_data()}: {err}" }
+            }
+        } else {
+            p { "{lang.read().no_consent()}" }
+        }
+    }
+}
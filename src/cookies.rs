@@ -9,17 +9,25 @@ fn html_document() -> Option<web_sys::HtmlDocument> {
 }
 
 /// Store (key, value) to cookies. No-op on non-web builds
-#[allow(unused_variables)]
 pub fn set_cookie(key: &str, value: &str) {
-    let cookie = format!(
+    write_cookie(&format!(
         "{key}={value}; Path=/; Max-Age={}; SameSite=Lax",
         2 * 365 * 24 * 60 * 60 // 2 years as max age
-    );
+    ));
+}
 
+/// Delete a cookie by letting it expire immediately
+pub fn delete_cookie(key: &str) {
+    write_cookie(&format!("{key}=; Path=/; Max-Age=0; SameSite=Lax"));
+}
+
+/// Write a cookie with its attributes, on the document or as a response header
+#[allow(unused_variables)]
+fn write_cookie(cookie: &str) {
     #[cfg(feature = "web")]
     {
         if let Some(document) = html_document() {
-            let _ = document.set_cookie(&cookie);
+            let _ = document.set_cookie(cookie);
         }
     }
     #[cfg(feature = "server")]
@@ -33,7 +41,7 @@ pub fn set_cookie(key: &str, value: &str) {
         if let (Some(context), Ok(header_name), Ok(header_value)) = (
             FullstackContext::current(),
             HeaderName::from_str("Set-Cookie"),
-            HeaderValue::from_str(&cookie),
+            HeaderValue::from_str(cookie),
         ) {
             context.add_response_header(header_name, header_value);
         }
@@ -13,14 +13,43 @@ pub struct Model {
     pub title: Option<String>,
     pub hidden: bool,
     pub views: i32,
+    pub estimated_old_views: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::daily_edition_reads::Entity")]
+    DailyEditionReads,
+    #[sea_orm(has_many = "super::daily_page_reads::Entity")]
+    DailyPageReads,
     #[sea_orm(has_many = "super::feedback::Entity")]
     Feedback,
+    #[sea_orm(has_many = "super::first_reads::Entity")]
+    FirstReads,
+    #[sea_orm(has_many = "super::flagged_traffic::Entity")]
+    FlaggedTraffic,
+    #[sea_orm(has_many = "super::read_progress::Entity")]
+    ReadProgress,
+    #[sea_orm(has_many = "super::read_sources::Entity")]
+    ReadSources,
     #[sea_orm(has_many = "super::reads::Entity")]
     Reads,
+    #[sea_orm(has_many = "super::session_editions::Entity")]
+    SessionEditions,
+    #[sea_orm(has_many = "super::views::Entity")]
+    Views,
+}
+
+impl Related<super::daily_edition_reads::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::DailyEditionReads.def()
+    }
+}
+
+impl Related<super::daily_page_reads::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::DailyPageReads.def()
+    }
 }
 
 impl Related<super::feedback::Entity> for Entity {
@@ -29,10 +58,46 @@ impl Related<super::feedback::Entity> for Entity {
     }
 }
 
+impl Related<super::first_reads::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::FirstReads.def()
+    }
+}
+
+impl Related<super::flagged_traffic::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::FlaggedTraffic.def()
+    }
+}
+
+impl Related<super::read_progress::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ReadProgress.def()
+    }
+}
+
+impl Related<super::read_sources::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ReadSources.def()
+    }
+}
+
 impl Related<super::reads::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Reads.def()
     }
 }
 
+impl Related<super::session_editions::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SessionEditions.def()
+    }
+}
+
+impl Related<super::views::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Views.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
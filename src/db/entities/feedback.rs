@@ -11,6 +11,7 @@ pub struct Model {
     pub content: String,
     pub email: Option<String>,
     pub edition_id: Option<i32>,
+    pub client_id: Option<Uuid>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -2,6 +2,18 @@
 
 pub mod prelude;
 
+pub mod daily_edition_reads;
+pub mod daily_page_reads;
 pub mod edition;
 pub mod feedback;
+pub mod first_reads;
+pub mod flagged_traffic;
+pub mod read_progress;
+pub mod read_sources;
 pub mod reads;
+pub mod session_editions;
+pub mod sessions;
+// only used by the exports on the server
+#[cfg(feature = "server")]
+pub mod uploaded_files;
+pub mod views;
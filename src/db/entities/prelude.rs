@@ -1,5 +1,16 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.19
 
+pub use super::daily_edition_reads::Entity as DailyEditionReads;
+pub use super::daily_page_reads::Entity as DailyPageReads;
 pub use super::edition::Entity as Edition;
 pub use super::feedback::Entity as Feedback;
+pub use super::first_reads::Entity as FirstReads;
+pub use super::flagged_traffic::Entity as FlaggedTraffic;
+pub use super::read_progress::Entity as ReadProgress;
+pub use super::read_sources::Entity as ReadSources;
 pub use super::reads::Entity as Reads;
+pub use super::session_editions::Entity as SessionEditions;
+pub use super::sessions::Entity as Sessions;
+#[cfg(feature = "server")]
+pub use super::uploaded_files::Entity as UploadedFiles;
+pub use super::views::Entity as Views;
@@ -14,6 +14,10 @@ pub struct Model {
     #[sea_orm(column_type = "Float")]
     pub read_time: f32,
     pub timestamp: TimeDateTime,
+    pub merge_window: Option<i64>,
+    #[sea_orm(column_type = "Float", nullable)]
+    pub active_read_time: Option<f32>,
+    pub session_id: Option<Uuid>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -4,10 +4,11 @@ use std::sync::OnceLock;
 static DB: OnceLock<DatabaseConnection> = OnceLock::new();
 
 /// Panics if connecting to db fails or DB is already set
-pub async fn init_db() {
-    let db = Database::connect("sqlite://mng.db?mode=rwc") // TODO: see what rwc means
+pub async fn init_db(url: &str) {
+    let mut db = Database::connect(url)
         .await
-        .expect("failed to connect to database");
+        .unwrap_or_else(|err| panic!("failed to connect to database {url}: {err}"));
+    db.set_metric_callback(crate::metrics::record_query);
 
     DB.set(db).expect("DB already set");
 }
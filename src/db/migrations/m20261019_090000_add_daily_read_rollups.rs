@@ -0,0 +1,129 @@
+//! Add daily rollups of the read event log, per edition page and per edition
+
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const PAGE_INDEX_NAME: &str = "index-daily-page-reads-by-day-edition-page";
+const EDITION_INDEX_NAME: &str = "index-daily-edition-reads-by-day-edition";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DailyPageReads::Table)
+                    .if_not_exists()
+                    .col(pk_auto(DailyPageReads::Id))
+                    .col(date(DailyPageReads::Day))
+                    .col(integer(DailyPageReads::EditionId))
+                    .col(integer(DailyPageReads::PageNumber))
+                    .col(integer(DailyPageReads::Readers))
+                    .col(integer(DailyPageReads::NewReaders))
+                    .col(float(DailyPageReads::ReadTime))
+                    .col(integer(DailyPageReads::Sessions))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(DailyPageReads::Table, DailyPageReads::EditionId)
+                            .to(Edition::Table, Edition::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(DailyPageReads::Table)
+                    .name(PAGE_INDEX_NAME)
+                    .col(DailyPageReads::Day)
+                    .col(DailyPageReads::EditionId)
+                    .col(DailyPageReads::PageNumber)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(DailyEditionReads::Table)
+                    .if_not_exists()
+                    .col(pk_auto(DailyEditionReads::Id))
+                    .col(date(DailyEditionReads::Day))
+                    .col(integer(DailyEditionReads::EditionId))
+                    .col(integer(DailyEditionReads::Readers))
+                    .col(integer(DailyEditionReads::NewReaders))
+                    .col(integer(DailyEditionReads::FirstTimeReaders))
+                    .col(float(DailyEditionReads::ReadTime))
+                    .col(integer(DailyEditionReads::Sessions))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(DailyEditionReads::Table, DailyEditionReads::EditionId)
+                            .to(Edition::Table, Edition::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(DailyEditionReads::Table)
+                    .name(EDITION_INDEX_NAME)
+                    .col(DailyEditionReads::Day)
+                    .col(DailyEditionReads::EditionId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DailyEditionReads::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(DailyPageReads::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum DailyPageReads {
+    Table,
+    Id,
+    Day,
+    EditionId,
+    PageNumber,
+    Readers,
+    NewReaders,
+    ReadTime,
+    Sessions,
+}
+
+#[derive(DeriveIden)]
+enum DailyEditionReads {
+    Table,
+    Id,
+    Day,
+    EditionId,
+    Readers,
+    NewReaders,
+    FirstTimeReaders,
+    ReadTime,
+    Sessions,
+}
+
+#[derive(DeriveIden)]
+enum Edition {
+    Table,
+    Id,
+}
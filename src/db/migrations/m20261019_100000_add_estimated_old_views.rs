@@ -0,0 +1,37 @@
+//! Add an estimate of the old views, approximated by read sessions
+
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Edition::Table)
+                    .add_column_if_not_exists(integer_null(Edition::EstimatedOldViews))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Edition::Table)
+                    .drop_column(Edition::EstimatedOldViews)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Edition {
+    Table,
+    EstimatedOldViews,
+}
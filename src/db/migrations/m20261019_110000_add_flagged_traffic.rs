@@ -0,0 +1,61 @@
+//! Add a table for views and reads flagged as bot traffic, which are kept out of the statistics
+
+use sea_orm_migration::{prelude::*, schema::*};
+use uuid::Uuid;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(FlaggedTraffic::Table)
+                    .if_not_exists()
+                    .col(pk_auto(FlaggedTraffic::Id))
+                    .col(string(FlaggedTraffic::Kind))
+                    .col(integer(FlaggedTraffic::EditionId))
+                    .col(uuid(FlaggedTraffic::ClientId).default(Uuid::nil()))
+                    .col(string_null(FlaggedTraffic::UserAgent))
+                    .col(string(FlaggedTraffic::Reason))
+                    .col(float(FlaggedTraffic::ReadTime).default(0.0))
+                    .col(timestamp(FlaggedTraffic::Timestamp).default(Expr::current_timestamp()))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(FlaggedTraffic::Table, FlaggedTraffic::EditionId)
+                            .to(Edition::Table, Edition::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FlaggedTraffic::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum FlaggedTraffic {
+    Table,
+    Id,
+    Kind,
+    EditionId,
+    ClientId,
+    UserAgent,
+    Reason,
+    ReadTime,
+    Timestamp,
+}
+
+#[derive(DeriveIden)]
+enum Edition {
+    Table,
+    Id,
+}
@@ -0,0 +1,77 @@
+//! Add a table of view events, so a client viewing an edition is only counted once per day
+
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const INDEX_NAME: &str = "index-views-by-client-edition-day";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Views::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Views::Id))
+                    .col(uuid(Views::ClientId))
+                    .col(integer(Views::EditionId))
+                    .col(date(Views::Day))
+                    .col(timestamp(Views::Timestamp).default(Expr::current_timestamp()))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Views::Table, Views::EditionId)
+                            .to(Edition::Table, Edition::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(Views::Table)
+                    .name(INDEX_NAME)
+                    .col(Views::ClientId)
+                    .col(Views::EditionId)
+                    .col(Views::Day)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(Views::Table)
+                    .name(INDEX_NAME)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Views::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Views {
+    Table,
+    Id,
+    ClientId,
+    EditionId,
+    Day,
+    Timestamp,
+}
+
+#[derive(DeriveIden)]
+enum Edition {
+    Table,
+    Id,
+}
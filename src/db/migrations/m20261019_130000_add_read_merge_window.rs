@@ -0,0 +1,65 @@
+//! Add the merge window of a read, so concurrent flushes can upsert into the same read
+
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const INDEX_NAME: &str = "index-reads-by-client-edition-page-merge-window";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // null for older reads, which may overlap in a window and are never merged into again
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reads::Table)
+                    .add_column_if_not_exists(big_integer_null(Reads::MergeWindow))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(Reads::Table)
+                    .name(INDEX_NAME)
+                    .col(Reads::ClientId)
+                    .col(Reads::EditionId)
+                    .col(Reads::PageNumber)
+                    .col(Reads::MergeWindow)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(Reads::Table)
+                    .name(INDEX_NAME)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reads::Table)
+                    .drop_column(Reads::MergeWindow)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Reads {
+    Table,
+    ClientId,
+    EditionId,
+    PageNumber,
+    MergeWindow,
+}
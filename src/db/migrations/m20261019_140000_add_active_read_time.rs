@@ -0,0 +1,38 @@
+//! Add the part of a read's time during which the reader was active (scrolling, touching, ...)
+
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // null for older reads, which didn't distinguish active from passive time
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reads::Table)
+                    .add_column_if_not_exists(float_null(Reads::ActiveReadTime))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reads::Table)
+                    .drop_column(Reads::ActiveReadTime)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Reads {
+    Table,
+    ActiveReadTime,
+}
@@ -0,0 +1,81 @@
+//! Add the furthest page a client reached in a reading session of an edition
+
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const INDEX_NAME: &str = "index-read-progress-by-client-session-edition";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ReadProgress::Table)
+                    .if_not_exists()
+                    .col(pk_auto(ReadProgress::Id))
+                    .col(uuid(ReadProgress::ClientId))
+                    .col(uuid(ReadProgress::SessionId))
+                    .col(integer(ReadProgress::EditionId))
+                    .col(integer(ReadProgress::FurthestPage))
+                    .col(boolean(ReadProgress::Finished))
+                    .col(timestamp(ReadProgress::Timestamp).default(Expr::current_timestamp()))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(ReadProgress::Table, ReadProgress::EditionId)
+                            .to(Edition::Table, Edition::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(ReadProgress::Table)
+                    .name(INDEX_NAME)
+                    .col(ReadProgress::ClientId)
+                    .col(ReadProgress::SessionId)
+                    .col(ReadProgress::EditionId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(ReadProgress::Table)
+                    .name(INDEX_NAME)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(ReadProgress::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ReadProgress {
+    Table,
+    Id,
+    ClientId,
+    SessionId,
+    EditionId,
+    FurthestPage,
+    Finished,
+    Timestamp,
+}
+
+#[derive(DeriveIden)]
+enum Edition {
+    Table,
+    Id,
+}
@@ -0,0 +1,87 @@
+//! Add where a reading session came from: entry point, referrer and `utm_*` parameters
+
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const INDEX_NAME: &str = "index-read-sources-by-client-session-edition";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ReadSources::Table)
+                    .if_not_exists()
+                    .col(pk_auto(ReadSources::Id))
+                    .col(uuid(ReadSources::ClientId))
+                    .col(uuid(ReadSources::SessionId))
+                    .col(integer(ReadSources::EditionId))
+                    .col(string(ReadSources::Entry))
+                    .col(string_null(ReadSources::Referrer))
+                    .col(string_null(ReadSources::UtmSource))
+                    .col(string_null(ReadSources::UtmMedium))
+                    .col(string_null(ReadSources::UtmCampaign))
+                    .col(timestamp(ReadSources::Timestamp).default(Expr::current_timestamp()))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(ReadSources::Table, ReadSources::EditionId)
+                            .to(Edition::Table, Edition::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(ReadSources::Table)
+                    .name(INDEX_NAME)
+                    .col(ReadSources::ClientId)
+                    .col(ReadSources::SessionId)
+                    .col(ReadSources::EditionId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(ReadSources::Table)
+                    .name(INDEX_NAME)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(ReadSources::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ReadSources {
+    Table,
+    Id,
+    ClientId,
+    SessionId,
+    EditionId,
+    Entry,
+    Referrer,
+    UtmSource,
+    UtmMedium,
+    UtmCampaign,
+    Timestamp,
+}
+
+#[derive(DeriveIden)]
+enum Edition {
+    Table,
+    Id,
+}
@@ -0,0 +1,58 @@
+//! Link feedback to the client that sent it, so readers can look up and delete their own feedback
+
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const INDEX_NAME: &str = "index-feedback-by-client";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // null for older feedback and feedback of readers without consent
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feedback::Table)
+                    .add_column_if_not_exists(uuid_null(Feedback::ClientId))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(Feedback::Table)
+                    .name(INDEX_NAME)
+                    .col(Feedback::ClientId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(Feedback::Table)
+                    .name(INDEX_NAME)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Feedback::Table)
+                    .drop_column(Feedback::ClientId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Feedback {
+    Table,
+    ClientId,
+}
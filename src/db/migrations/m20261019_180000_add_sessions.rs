@@ -0,0 +1,187 @@
+//! Add reading sessions (one per page load), the editions touched in them, and reference them
+//! from reads. Reads of different sessions in the same merge window are no longer merged.
+
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const SESSION_INDEX_NAME: &str = "index-sessions-by-session";
+const SESSION_EDITIONS_INDEX_NAME: &str = "index-session-editions-by-session-edition";
+const OLD_READS_INDEX_NAME: &str = "index-reads-by-client-edition-page-merge-window";
+const READS_INDEX_NAME: &str = "index-reads-by-client-edition-page-merge-window-session";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Sessions::Table)
+                    .if_not_exists()
+                    .col(pk_auto(Sessions::Id))
+                    .col(uuid(Sessions::SessionId))
+                    .col(uuid(Sessions::ClientId))
+                    .col(timestamp(Sessions::Start).default(Expr::current_timestamp()))
+                    .col(timestamp(Sessions::End).default(Expr::current_timestamp()))
+                    .col(string(Sessions::DeviceClass))
+                    .col(string(Sessions::Entry))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .table(Sessions::Table)
+                    .name(SESSION_INDEX_NAME)
+                    .col(Sessions::SessionId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SessionEditions::Table)
+                    .if_not_exists()
+                    .col(pk_auto(SessionEditions::Id))
+                    .col(uuid(SessionEditions::SessionId))
+                    .col(integer(SessionEditions::EditionId))
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(SessionEditions::Table, SessionEditions::SessionId)
+                            .to(Sessions::Table, Sessions::SessionId)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(SessionEditions::Table, SessionEditions::EditionId)
+                            .to(Edition::Table, Edition::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .table(SessionEditions::Table)
+                    .name(SESSION_EDITIONS_INDEX_NAME)
+                    .col(SessionEditions::SessionId)
+                    .col(SessionEditions::EditionId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // null for older reads
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reads::Table)
+                    .add_column_if_not_exists(uuid_null(Reads::SessionId))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(Reads::Table)
+                    .name(OLD_READS_INDEX_NAME)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .table(Reads::Table)
+                    .name(READS_INDEX_NAME)
+                    .col(Reads::ClientId)
+                    .col(Reads::EditionId)
+                    .col(Reads::PageNumber)
+                    .col(Reads::MergeWindow)
+                    .col(Reads::SessionId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(Reads::Table)
+                    .name(READS_INDEX_NAME)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Reads::Table)
+                    .drop_column(Reads::SessionId)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .table(Reads::Table)
+                    .name(OLD_READS_INDEX_NAME)
+                    .col(Reads::ClientId)
+                    .col(Reads::EditionId)
+                    .col(Reads::PageNumber)
+                    .col(Reads::MergeWindow)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(SessionEditions::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Sessions::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Sessions {
+    Table,
+    Id,
+    SessionId,
+    ClientId,
+    Start,
+    End,
+    DeviceClass,
+    Entry,
+}
+
+#[derive(DeriveIden)]
+enum SessionEditions {
+    Table,
+    Id,
+    SessionId,
+    EditionId,
+}
+
+#[derive(DeriveIden)]
+enum Reads {
+    Table,
+    ClientId,
+    EditionId,
+    PageNumber,
+    MergeWindow,
+    SessionId,
+}
+
+#[derive(DeriveIden)]
+enum Edition {
+    Table,
+    Id,
+}
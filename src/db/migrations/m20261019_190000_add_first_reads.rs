@@ -0,0 +1,96 @@
+//! Add the day each client first read each page, so new readers can still be told apart once the
+//! raw reads are deleted or anonymized by the retention policy
+
+use sea_orm_migration::{prelude::*, schema::*, sea_orm::prelude::Uuid};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(FirstReads::Table)
+                    .if_not_exists()
+                    .col(uuid(FirstReads::ClientId))
+                    .col(integer(FirstReads::EditionId))
+                    .col(integer(FirstReads::PageNumber))
+                    .col(date(FirstReads::Day))
+                    .primary_key(
+                        Index::create()
+                            .col(FirstReads::ClientId)
+                            .col(FirstReads::EditionId)
+                            .col(FirstReads::PageNumber),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(FirstReads::Table, FirstReads::EditionId)
+                            .to(Edition::Table, Edition::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // backfill from the reads that weren't anonymized yet
+        manager
+            .exec_stmt(
+                Query::insert()
+                    .into_table(FirstReads::Table)
+                    .columns([
+                        FirstReads::ClientId,
+                        FirstReads::EditionId,
+                        FirstReads::PageNumber,
+                        FirstReads::Day,
+                    ])
+                    .select_from(
+                        Query::select()
+                            .columns([Reads::ClientId, Reads::EditionId, Reads::PageNumber])
+                            .expr(Expr::cust("MIN(DATE(\"timestamp\"))"))
+                            .from(Reads::Table)
+                            .and_where(Expr::col(Reads::ClientId).ne(Uuid::nil()))
+                            .group_by_columns([
+                                Reads::ClientId,
+                                Reads::EditionId,
+                                Reads::PageNumber,
+                            ])
+                            .to_owned(),
+                    )
+                    .map_err(|err| DbErr::Migration(err.to_string()))?
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FirstReads::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum FirstReads {
+    Table,
+    ClientId,
+    EditionId,
+    PageNumber,
+    Day,
+}
+
+#[derive(DeriveIden)]
+enum Reads {
+    Table,
+    ClientId,
+    EditionId,
+    PageNumber,
+}
+
+#[derive(DeriveIden)]
+enum Edition {
+    Table,
+    Id,
+}
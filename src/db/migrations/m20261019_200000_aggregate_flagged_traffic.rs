@@ -0,0 +1,126 @@
+//! Aggregate flagged traffic per day, so bots can't grow `flagged_traffic` without bound.
+//! Flagged views and reads with the same kind, edition, client id, User-Agent (truncated to 256
+//! characters, empty if missing) and reason are counted in a single row per day.
+
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+const INDEX_NAME: &str = "index-flagged-traffic-by-kind-edition-client-user-agent-reason-day";
+
+/// The columns flagged traffic is aggregated by
+const KEY: [&str; 6] = [
+    "kind",
+    "edition_id",
+    "client_id",
+    "user_agent",
+    "reason",
+    "day",
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FlaggedTraffic::Table)
+                    .add_column_if_not_exists(date(FlaggedTraffic::Day).default("1970-01-01"))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FlaggedTraffic::Table)
+                    .add_column_if_not_exists(integer(FlaggedTraffic::Occurrences).default(1))
+                    .to_owned(),
+            )
+            .await?;
+
+        // collapse the existing rows into the first row of each key. The key has no nulls once
+        // missing User-Agents are empty.
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            r#"UPDATE flagged_traffic
+            SET day = DATE("timestamp"), user_agent = SUBSTR(COALESCE(user_agent, ''), 1, 256)"#,
+        )
+        .await?;
+
+        let key = KEY.join(", ");
+        let same_key = KEY
+            .iter()
+            .map(|column| format!("other.{column} = flagged_traffic.{column}"))
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        db.execute_unprepared(&format!(
+            "UPDATE flagged_traffic
+            SET
+                occurrences = (SELECT COUNT(*) FROM flagged_traffic AS other WHERE {same_key}),
+                read_time = (SELECT SUM(read_time) FROM flagged_traffic AS other WHERE {same_key})
+            WHERE id IN (SELECT MIN(id) FROM flagged_traffic GROUP BY {key})"
+        ))
+        .await?;
+        db.execute_unprepared(&format!(
+            "DELETE FROM flagged_traffic
+            WHERE id NOT IN (SELECT MIN(id) FROM flagged_traffic GROUP BY {key})"
+        ))
+        .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .table(FlaggedTraffic::Table)
+                    .name(INDEX_NAME)
+                    .col(FlaggedTraffic::Kind)
+                    .col(FlaggedTraffic::EditionId)
+                    .col(FlaggedTraffic::ClientId)
+                    .col(FlaggedTraffic::UserAgent)
+                    .col(FlaggedTraffic::Reason)
+                    .col(FlaggedTraffic::Day)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .table(FlaggedTraffic::Table)
+                    .name(INDEX_NAME)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FlaggedTraffic::Table)
+                    .drop_column(FlaggedTraffic::Occurrences)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FlaggedTraffic::Table)
+                    .drop_column(FlaggedTraffic::Day)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum FlaggedTraffic {
+    Table,
+    Kind,
+    EditionId,
+    ClientId,
+    UserAgent,
+    Reason,
+    Day,
+    Occurrences,
+}
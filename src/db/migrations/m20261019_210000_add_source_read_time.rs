@@ -0,0 +1,82 @@
+//! Store the read time of each read source, so the read time per source survives the retention
+//! policy like the readers per source do
+
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ReadSources::Table)
+                    .add_column_if_not_exists(float(ReadSources::ReadTime).default(0.0))
+                    .to_owned(),
+            )
+            .await?;
+
+        // backfill from the reads of each session
+        let read_time = Query::select()
+            .expr(Func::coalesce([
+                Expr::col((Reads::Table, Reads::ReadTime)).sum(),
+                Expr::val(0.0).into(),
+            ]))
+            .from(Reads::Table)
+            .and_where(
+                Expr::col((Reads::Table, Reads::ClientId))
+                    .equals((ReadSources::Table, ReadSources::ClientId)),
+            )
+            .and_where(
+                Expr::col((Reads::Table, Reads::SessionId))
+                    .equals((ReadSources::Table, ReadSources::SessionId)),
+            )
+            .and_where(
+                Expr::col((Reads::Table, Reads::EditionId))
+                    .equals((ReadSources::Table, ReadSources::EditionId)),
+            )
+            .to_owned();
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(ReadSources::Table)
+                    .value(
+                        ReadSources::ReadTime,
+                        SimpleExpr::SubQuery(None, Box::new(read_time.into_sub_query_statement())),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ReadSources::Table)
+                    .drop_column(ReadSources::ReadTime)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ReadSources {
+    Table,
+    ClientId,
+    SessionId,
+    EditionId,
+    ReadTime,
+}
+
+#[derive(DeriveIden)]
+enum Reads {
+    Table,
+    ClientId,
+    SessionId,
+    EditionId,
+    ReadTime,
+}
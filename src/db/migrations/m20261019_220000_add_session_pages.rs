@@ -0,0 +1,73 @@
+//! Store the distinct pages of each edition read in a session, so pages per session don't depend on
+//! the raw reads, which may be deleted by the retention policy
+
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SessionEditions::Table)
+                    .add_column_if_not_exists(integer(SessionEditions::Pages).default(0))
+                    .to_owned(),
+            )
+            .await?;
+
+        // backfill from the reads of each session
+        let pages = Query::select()
+            .expr(Expr::col((Reads::Table, Reads::PageNumber)).count_distinct())
+            .from(Reads::Table)
+            .and_where(
+                Expr::col((Reads::Table, Reads::SessionId))
+                    .equals((SessionEditions::Table, SessionEditions::SessionId)),
+            )
+            .and_where(
+                Expr::col((Reads::Table, Reads::EditionId))
+                    .equals((SessionEditions::Table, SessionEditions::EditionId)),
+            )
+            .to_owned();
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(SessionEditions::Table)
+                    .value(
+                        SessionEditions::Pages,
+                        SimpleExpr::SubQuery(None, Box::new(pages.into_sub_query_statement())),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SessionEditions::Table)
+                    .drop_column(SessionEditions::Pages)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum SessionEditions {
+    Table,
+    SessionId,
+    EditionId,
+    Pages,
+}
+
+#[derive(DeriveIden)]
+enum Reads {
+    Table,
+    SessionId,
+    EditionId,
+    PageNumber,
+}
@@ -0,0 +1,38 @@
+//! Add the hash of the last uploaded content of each exported file, so unchanged exports aren't
+//! uploaded again after a restart
+
+use sea_orm_migration::{prelude::*, schema::*};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(UploadedFiles::Table)
+                    .if_not_exists()
+                    .col(string(UploadedFiles::FileName).primary_key())
+                    .col(big_integer(UploadedFiles::Hash))
+                    .col(timestamp(UploadedFiles::Timestamp).default(Expr::current_timestamp()))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UploadedFiles::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum UploadedFiles {
+    Table,
+    FileName,
+    Hash,
+    Timestamp,
+}
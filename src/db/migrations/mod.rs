@@ -9,6 +9,21 @@ mod m20251210_110103_add_hidden_flag_to_edition;
 mod m20260203_204144_read_event_log;
 mod m20260214_134252_mark_old_views;
 mod m20260214_141218_add_read_timestamp;
+mod m20261019_090000_add_daily_read_rollups;
+mod m20261019_100000_add_estimated_old_views;
+mod m20261019_110000_add_flagged_traffic;
+mod m20261019_120000_add_view_events;
+mod m20261019_130000_add_read_merge_window;
+mod m20261019_140000_add_active_read_time;
+mod m20261019_150000_add_read_progress;
+mod m20261019_160000_add_read_sources;
+mod m20261019_170000_add_feedback_client_id;
+mod m20261019_180000_add_sessions;
+mod m20261019_190000_add_first_reads;
+mod m20261019_200000_aggregate_flagged_traffic;
+mod m20261019_210000_add_source_read_time;
+mod m20261019_220000_add_session_pages;
+mod m20261019_230000_add_uploaded_files;
 
 pub struct Migrator;
 
@@ -24,6 +39,21 @@ impl MigratorTrait for Migrator {
             Box::new(m20260203_204144_read_event_log::Migration),
             Box::new(m20260214_134252_mark_old_views::Migration),
             Box::new(m20260214_141218_add_read_timestamp::Migration),
+            Box::new(m20261019_090000_add_daily_read_rollups::Migration),
+            Box::new(m20261019_100000_add_estimated_old_views::Migration),
+            Box::new(m20261019_110000_add_flagged_traffic::Migration),
+            Box::new(m20261019_120000_add_view_events::Migration),
+            Box::new(m20261019_130000_add_read_merge_window::Migration),
+            Box::new(m20261019_140000_add_active_read_time::Migration),
+            Box::new(m20261019_150000_add_read_progress::Migration),
+            Box::new(m20261019_160000_add_read_sources::Migration),
+            Box::new(m20261019_170000_add_feedback_client_id::Migration),
+            Box::new(m20261019_180000_add_sessions::Migration),
+            Box::new(m20261019_190000_add_first_reads::Migration),
+            Box::new(m20261019_200000_aggregate_flagged_traffic::Migration),
+            Box::new(m20261019_210000_add_source_read_time::Migration),
+            Box::new(m20261019_220000_add_session_pages::Migration),
+            Box::new(m20261019_230000_add_uploaded_files::Migration),
         ]
     }
 }
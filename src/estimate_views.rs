@@ -0,0 +1,116 @@
+//! Approximate the views counted by the old view counter from read sessions.
+//!
+//! The old counter was moved to `old_views` when views started being counted anew (see
+//! `m20260214_134252_mark_old_views`). Since then both `views` and the read sessions in the daily
+//! rollups are recorded, which calibrates how many views one read session corresponds to. That ratio
+//! is applied to the sessions of each edition from before the switch.
+//!
+//! Reads are only logged since `READ_LOGGING_START`, so only editions published since then have read
+//! sessions for all of their old views. Older editions can't be estimated.
+
+use crate::{
+    components::EditionId,
+    db::{
+        db,
+        entities::{daily_edition_reads, edition},
+    },
+};
+use dioxus::prelude::*;
+use sea_orm::{ActiveModelTrait, EntityTrait, QuerySelect, Set};
+use std::collections::HashMap;
+use time::{Date, Month};
+
+/// The day reads started being logged (see `m20260203_204144_read_event_log`)
+const READ_LOGGING_START: Date = match Date::from_calendar_date(2026, Month::February, 3) {
+    Ok(date) => date,
+    Err(_) => panic!("invalid read logging start"),
+};
+/// The day `views` started being counted anew
+const VIEW_COUNTING_START: Date = match Date::from_calendar_date(2026, Month::February, 14) {
+    Ok(date) => date,
+    Err(_) => panic!("invalid view counting start"),
+};
+
+#[derive(Default)]
+struct Sessions {
+    before: i64,
+    after: i64,
+}
+
+/// Views per read session, over all editions with both views and sessions since the switch
+fn views_per_session(
+    editions: &[edition::Model],
+    sessions: &HashMap<EditionId, Sessions>,
+) -> Option<f64> {
+    let (views, after) = editions
+        .iter()
+        .filter_map(|edition| {
+            let after = sessions.get(&edition.id)?.after;
+            (edition.views > 0 && after > 0).then_some((edition.views as i64, after))
+        })
+        .fold(
+            (0, 0),
+            |(views, sessions), (edition_views, edition_sessions)| {
+                (views + edition_views, sessions + edition_sessions)
+            },
+        );
+
+    (after > 0).then(|| views as f64 / after as f64)
+}
+
+/// Update the estimated old views of every edition published since `READ_LOGGING_START` with old
+/// views and read sessions from before the switch. All other editions can't be estimated and are set
+/// to `None`.
+pub async fn estimate_old_views() -> Result<()> {
+    let db = db();
+
+    let editions = edition::Entity::find()
+        .all(db)
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to get editions: {err}")))?;
+
+    let daily_sessions: Vec<(EditionId, Date, i32)> = daily_edition_reads::Entity::find()
+        .select_only()
+        .column(daily_edition_reads::Column::EditionId)
+        .column(daily_edition_reads::Column::Day)
+        .column(daily_edition_reads::Column::Sessions)
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to get daily sessions: {err}")))?;
+
+    let mut sessions = HashMap::<EditionId, Sessions>::new();
+    for (edition_id, day, day_sessions) in daily_sessions {
+        let edition_sessions = sessions.entry(edition_id).or_default();
+        if day < VIEW_COUNTING_START {
+            edition_sessions.before += day_sessions as i64;
+        } else {
+            edition_sessions.after += day_sessions as i64;
+        }
+    }
+
+    let Some(views_per_session) = views_per_session(&editions, &sessions) else {
+        return Ok(());
+    };
+
+    for edition in editions {
+        let estimate = sessions
+            .get(&edition.id)
+            .filter(|sessions| {
+                edition.date >= READ_LOGGING_START && edition.old_views > 0 && sessions.before > 0
+            })
+            .map(|sessions| (sessions.before as f64 * views_per_session).round() as i32);
+
+        if estimate == edition.estimated_old_views {
+            continue;
+        }
+
+        let mut active: edition::ActiveModel = edition.into();
+        active.estimated_old_views = Set(estimate);
+        active.update(db).await.map_err(|err| {
+            ServerFnError::new(format!("Failed to update estimated old views: {err}"))
+        })?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,437 @@
+//! Exports as CSV (RFC 4180), JSON and XLSX.
+//!
+//! An export consists of tables of serializable rows and summary values. Its columns are the
+//! serialized field names of the rows, in declaration order. Rows should only contain numbers,
+//! bools, strings and `None`, with dates as `YYYY-MM-DD` strings in columns declared as dates and
+//! durations in seconds, so every format gets the right types.
+//!
+//! - CSV: one file per table (`<table>.csv`), and `<export>_summary.csv` if there are summary values
+//! - JSON: `<export>.json`, an object with an array of rows per table and a `summary` object
+//! - XLSX: `<export>.xlsx`, a sheet per table and a summary sheet with the summary values and the
+//!   row count of every table
+
+use dioxus::prelude::*;
+use rust_xlsxwriter::{DocProperties, ExcelDateTime, Format as CellFormat, Workbook};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use time::{Date, macros::format_description};
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Csv,
+    Json,
+    Xlsx,
+}
+
+pub struct Table {
+    name: &'static str,
+    columns: Vec<String>,
+    /// Whether each column holds dates
+    dates: Vec<bool>,
+    rows: Vec<Vec<Value>>,
+}
+
+pub struct Export {
+    name: &'static str,
+    tables: Vec<Table>,
+    summary: Vec<(&'static str, Value)>,
+}
+
+/// Parse a `YYYY-MM-DD` date
+fn parse_date(value: &str) -> Option<Date> {
+    Date::parse(value, format_description!("[year]-[month]-[day]")).ok()
+}
+
+/// The fields of a row as (name, value) pairs
+fn fields(row: &impl Serialize) -> Result<Map<String, Value>, ServerFnError> {
+    match serde_json::to_value(row) {
+        Ok(Value::Object(fields)) => Ok(fields),
+        Ok(other) => Err(ServerFnError::new(format!(
+            "Export rows have to be structs, got {other}"
+        ))),
+        Err(err) => Err(ServerFnError::new(format!(
+            "Failed to serialize row: {err}"
+        ))),
+    }
+}
+
+impl Export {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            tables: Vec::new(),
+            summary: Vec::new(),
+        }
+    }
+
+    /// Add a table. The columns are taken from the default row, so empty tables have them too.
+    pub fn table<T: Serialize + Default>(
+        self,
+        name: &'static str,
+        rows: impl IntoIterator<Item = T>,
+    ) -> Result<Self, ServerFnError> {
+        self.table_with_dates(name, &[], rows)
+    }
+
+    /// Add a table whose `dates` columns hold `YYYY-MM-DD` dates, which become date cells in XLSX.
+    /// Strings in other columns stay strings, even if they look like dates.
+    pub fn table_with_dates<T: Serialize + Default>(
+        mut self,
+        name: &'static str,
+        dates: &[&str],
+        rows: impl IntoIterator<Item = T>,
+    ) -> Result<Self, ServerFnError> {
+        let columns = fields(&T::default())?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        if let Some(missing) = dates
+            .iter()
+            .find(|date| !columns.contains(&date.to_string()))
+        {
+            return Err(ServerFnError::new(format!(
+                "Date column {missing} is not a column of table {name}"
+            )));
+        }
+        let dates = columns
+            .iter()
+            .map(|column| dates.contains(&column.as_str()))
+            .collect();
+        let rows = rows
+            .into_iter()
+            .map(|row| Ok(fields(&row)?.into_iter().map(|(_, value)| value).collect()))
+            .collect::<Result<_, ServerFnError>>()?;
+
+        self.tables.push(Table {
+            name,
+            columns,
+            dates,
+            rows,
+        });
+        Ok(self)
+    }
+
+    /// Add a summary value
+    pub fn summary(mut self, name: &'static str, value: impl Into<Value>) -> Self {
+        self.summary.push((name, value.into()));
+        self
+    }
+
+    /// The files of the export in the format, as (file name, content)
+    pub fn files(&self, format: Format) -> Result<Vec<(String, Vec<u8>)>, ServerFnError> {
+        match format {
+            Format::Csv => {
+                let mut files = self
+                    .tables
+                    .iter()
+                    .map(|table| {
+                        let rows = table.rows.iter().map(|row| row.iter().map(csv_field));
+                        Ok((format!("{}.csv", table.name), csv(&table.columns, rows)?))
+                    })
+                    .collect::<Result<Vec<_>, ServerFnError>>()?;
+
+                if !self.summary.is_empty() {
+                    let rows = self
+                        .summary
+                        .iter()
+                        .map(|(name, value)| [name.to_string(), csv_field(value)].into_iter());
+                    files.push((
+                        format!("{}_summary.csv", self.name),
+                        csv(&["Name", "Value"], rows)?,
+                    ));
+                }
+
+                Ok(files)
+            }
+            Format::Json => {
+                let mut export = self
+                    .tables
+                    .iter()
+                    .map(|table| {
+                        let rows = table
+                            .rows
+                            .iter()
+                            .map(|row| {
+                                Value::Object(
+                                    table.columns.iter().cloned().zip(row.clone()).collect(),
+                                )
+                            })
+                            .collect();
+                        (table.name.to_string(), Value::Array(rows))
+                    })
+                    .collect::<Map<_, _>>();
+                export.insert(
+                    "summary".to_string(),
+                    Value::Object(
+                        self.summary
+                            .iter()
+                            .map(|(name, value)| (name.to_string(), value.clone()))
+                            .collect(),
+                    ),
+                );
+
+                let json = serde_json::to_vec_pretty(&export)
+                    .map_err(|err| ServerFnError::new(format!("Failed to write JSON: {err}")))?;
+                Ok(vec![(format!("{}.json", self.name), json)])
+            }
+            Format::Xlsx => Ok(vec![(format!("{}.xlsx", self.name), self.xlsx()?)]),
+        }
+    }
+
+    fn xlsx(&self) -> Result<Vec<u8>, ServerFnError> {
+        let err = |err: rust_xlsxwriter::XlsxError| {
+            ServerFnError::new(format!("Failed to write {}.xlsx: {err}", self.name))
+        };
+
+        let header = CellFormat::new().set_bold();
+        let date = CellFormat::new().set_num_format("yyyy-mm-dd");
+
+        let mut workbook = Workbook::new();
+        // a fixed creation date, so unchanged exports stay byte for byte the same
+        workbook.set_properties(
+            &DocProperties::new()
+                .set_creation_datetime(&ExcelDateTime::from_ymd(2000, 1, 1).map_err(err)?),
+        );
+
+        for table in &self.tables {
+            let sheet = workbook.add_worksheet();
+            sheet.set_name(table.name).map_err(err)?;
+            sheet.set_freeze_panes(1, 0).map_err(err)?;
+
+            for (col, column) in table.columns.iter().enumerate() {
+                sheet
+                    .write_string_with_format(0, col as u16, column, &header)
+                    .map_err(err)?;
+            }
+            for (row, values) in table.rows.iter().enumerate() {
+                let row = row as u32 + 1;
+                for (col, value) in values.iter().enumerate() {
+                    let col = col as u16;
+                    match value {
+                        Value::Null => {}
+                        Value::Bool(bool) => {
+                            sheet.write_boolean(row, col, *bool).map_err(err)?;
+                        }
+                        Value::Number(number) => {
+                            let number = number.as_f64().unwrap_or_default();
+                            sheet.write_number(row, col, number).map_err(err)?;
+                        }
+                        Value::String(string) => match table.dates[col as usize]
+                            .then(|| parse_date(string))
+                            .flatten()
+                        {
+                            Some(day) => {
+                                let day = ExcelDateTime::from_ymd(
+                                    day.year() as u16,
+                                    day.month() as u8,
+                                    day.day(),
+                                )
+                                .map_err(err)?;
+                                sheet
+                                    .write_datetime_with_format(row, col, &day, &date)
+                                    .map_err(err)?;
+                            }
+                            None => {
+                                sheet.write_string(row, col, string).map_err(err)?;
+                            }
+                        },
+                        other => {
+                            sheet
+                                .write_string(row, col, other.to_string())
+                                .map_err(err)?;
+                        }
+                    }
+                }
+            }
+            sheet.autofit();
+        }
+
+        let summary = workbook.add_worksheet();
+        summary.set_name("Summary").map_err(err)?;
+        summary
+            .write_string_with_format(0, 0, "Name", &header)
+            .map_err(err)?;
+        summary
+            .write_string_with_format(0, 1, "Value", &header)
+            .map_err(err)?;
+        let row_counts = self.tables.iter().map(|table| {
+            (
+                format!("{} rows", table.name),
+                Value::from(table.rows.len()),
+            )
+        });
+        let values = self
+            .summary
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .chain(row_counts);
+        for (row, (name, value)) in values.enumerate() {
+            let row = row as u32 + 1;
+            summary.write_string(row, 0, name).map_err(err)?;
+            match value {
+                Value::Number(number) => {
+                    summary.write_number(row, 1, number.as_f64().unwrap_or_default())
+                }
+                value => summary.write_string(row, 1, csv_field(&value)),
+            }
+            .map_err(err)?;
+        }
+        summary.autofit();
+
+        workbook.save_to_buffer().map_err(err)
+    }
+}
+
+/// A value as CSV field, unquoted
+fn csv_field(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(string) => string.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Write a header and rows as RFC 4180 CSV: CRLF line endings, fields quoted only if needed
+fn csv<Row: Iterator<Item = impl AsRef<[u8]>>>(
+    header: &[impl AsRef<[u8]>],
+    rows: impl Iterator<Item = Row>,
+) -> Result<Vec<u8>, ServerFnError> {
+    let err = |err: csv::Error| ServerFnError::new(format!("Failed to write CSV: {err}"));
+
+    let mut writer = csv::WriterBuilder::new()
+        .terminator(csv::Terminator::CRLF)
+        .from_writer(Vec::new());
+    writer.write_record(header).map_err(err)?;
+    for row in rows {
+        writer.write_record(row).map_err(err)?;
+    }
+
+    writer
+        .into_inner()
+        .map_err(|err| ServerFnError::new(format!("Failed to write CSV: {err}")))
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+
+    #[derive(Default, Serialize)]
+    struct Row {
+        #[serde(rename = "Date")]
+        date: String,
+        #[serde(rename = "Title")]
+        title: String,
+        #[serde(rename = "Reads")]
+        reads: u64,
+    }
+
+    fn row(date: &str, title: &str, reads: u64) -> Row {
+        Row {
+            date: date.to_string(),
+            title: title.to_string(),
+            reads,
+        }
+    }
+
+    fn export() -> Export {
+        Export::new("export")
+            .table_with_dates(
+                "reads",
+                &["Date"],
+                [
+                    row("2026-02-03", "2026-02-04", 3),
+                    row("2026-02-04", "Nr. 5", 1),
+                ],
+            )
+            .unwrap()
+            .summary("Total", 4)
+    }
+
+    fn text(content: &[u8]) -> &str {
+        std::str::from_utf8(content).unwrap()
+    }
+
+    #[test]
+    fn csv_quotes_only_when_needed_and_ends_lines_with_crlf() {
+        let rows = [["plain", "a,b"], ["say \"hi\"", "two\nlines"]];
+        let csv = csv(&["Name", "Value"], rows.iter().map(|row| row.iter())).unwrap();
+        assert_eq!(
+            text(&csv),
+            "Name,Value\r\nplain,\"a,b\"\r\n\"say \"\"hi\"\"\",\"two\nlines\"\r\n"
+        );
+    }
+
+    #[test]
+    fn only_declared_columns_are_dates() {
+        let export = export();
+        assert_eq!(export.tables[0].dates, [true, false, false]);
+    }
+
+    #[test]
+    fn rejects_unknown_date_columns() {
+        assert!(
+            Export::new("export")
+                .table_with_dates("reads", &["Day"], Vec::<Row>::new())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn csv_has_a_file_per_table_and_a_summary() {
+        let files = export().files(Format::Csv).unwrap();
+        let files = files
+            .iter()
+            .map(|(name, content)| (name.as_str(), text(content)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            files,
+            [
+                (
+                    "reads.csv",
+                    "Date,Title,Reads\r\n2026-02-03,2026-02-04,3\r\n2026-02-04,Nr. 5,1\r\n"
+                ),
+                ("export_summary.csv", "Name,Value\r\nTotal,4\r\n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_of_empty_table_has_header() {
+        let files = Export::new("export")
+            .table("reads", Vec::<Row>::new())
+            .unwrap()
+            .files(Format::Csv)
+            .unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].0, "reads.csv");
+        assert_eq!(text(&files[0].1), "Date,Title,Reads\r\n");
+    }
+
+    #[test]
+    fn json_has_rows_per_table_and_summary() {
+        let files = export().files(Format::Json).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].0, "export.json");
+        let json: Value = serde_json::from_slice(&files[0].1).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "reads": [
+                    { "Date": "2026-02-03", "Title": "2026-02-04", "Reads": 3 },
+                    { "Date": "2026-02-04", "Title": "Nr. 5", "Reads": 1 },
+                ],
+                "summary": { "Total": 4 },
+            })
+        );
+    }
+
+    #[test]
+    fn xlsx_is_a_single_reproducible_workbook() {
+        let files = export().files(Format::Xlsx).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].0, "export.xlsx");
+        assert!(files[0].1.starts_with(b"PK\x03\x04"));
+        assert_eq!(files, export().files(Format::Xlsx).unwrap());
+    }
+}
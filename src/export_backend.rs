@@ -0,0 +1,232 @@
+//! Where exports are uploaded to and the team is downloaded from: the Infomaniak kDrive API, a
+//! local directory or a WebDAV collection, selected by `export.backend` (see `config`).
+
+use crate::config::{BackendKind, ExportConfig, config};
+use dioxus::{
+    fullstack::{
+        reqwest::{self, Url},
+        reqwest_response_to_serverfn_err,
+    },
+    prelude::*,
+};
+use serde::Deserialize;
+use std::{path::PathBuf, sync::LazyLock};
+
+static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(reqwest::Client::new);
+
+/// The configured backend
+pub static BACKEND: LazyLock<Backend> = LazyLock::new(|| Backend::from_config(&config().export));
+
+pub trait ExportBackend {
+    /// Store a file, replacing any earlier version of it
+    fn upload(&self, file_name: &str, content: Vec<u8>) -> impl Future<Output = Result<()>> + Send;
+
+    /// Read a file
+    fn download(&self, file_name: &str) -> impl Future<Output = Result<Vec<u8>>> + Send;
+}
+
+pub enum Backend {
+    KDrive(KDrive),
+    Local(LocalDirectory),
+    WebDav(WebDav),
+}
+impl Backend {
+    /// From a validated config
+    pub fn from_config(config: &ExportConfig) -> Self {
+        match config.backend {
+            BackendKind::KDrive => {
+                let kdrive = &config.kdrive;
+                let required = |value: &Option<String>| {
+                    value
+                        .clone()
+                        .expect("kDrive config should be validated")
+                        .trim()
+                        .to_string()
+                };
+
+                Self::KDrive(KDrive {
+                    drive_id: required(&kdrive.drive_id),
+                    oauth_token: required(&kdrive.oauth_token),
+                    directory_id: required(&kdrive.directory_id),
+                    file_id: required(&kdrive.file_id),
+                })
+            }
+            BackendKind::Local => Self::Local(LocalDirectory {
+                path: config.dir.clone(),
+            }),
+            BackendKind::WebDav => {
+                let webdav = &config.webdav;
+                let mut url = webdav
+                    .url
+                    .as_deref()
+                    .and_then(|url| Url::parse(url).ok())
+                    .expect("WebDAV url should be validated");
+                // file names are joined onto the collection, which needs a trailing slash for that
+                if !url.path().ends_with('/') {
+                    url.set_path(&format!("{}/", url.path()));
+                }
+
+                Self::WebDav(WebDav {
+                    url,
+                    user: webdav.user.clone(),
+                    password: webdav.password.clone(),
+                })
+            }
+        }
+    }
+}
+impl ExportBackend for Backend {
+    async fn upload(&self, file_name: &str, content: Vec<u8>) -> Result<()> {
+        match self {
+            Self::KDrive(backend) => backend.upload(file_name, content).await,
+            Self::Local(backend) => backend.upload(file_name, content).await,
+            Self::WebDav(backend) => backend.upload(file_name, content).await,
+        }
+    }
+
+    async fn download(&self, file_name: &str) -> Result<Vec<u8>> {
+        match self {
+            Self::KDrive(backend) => backend.download(file_name).await,
+            Self::Local(backend) => backend.download(file_name).await,
+            Self::WebDav(backend) => backend.download(file_name).await,
+        }
+    }
+}
+
+/// The Infomaniak kDrive API. Uploads go to `directory_id` as new versions of existing files.
+/// kDrive only downloads by file id, so the one downloaded file (the team) is `file_id`.
+pub struct KDrive {
+    drive_id: String,
+    oauth_token: String,
+    directory_id: String,
+    file_id: String,
+}
+
+#[derive(Deserialize)]
+struct UploadResponse {
+    result: String,
+}
+
+impl ExportBackend for KDrive {
+    async fn upload(&self, file_name: &str, content: Vec<u8>) -> Result<()> {
+        let url = Url::parse_with_params(
+            &format!(
+                "https://api.infomaniak.com/3/drive/{}/upload",
+                self.drive_id
+            ),
+            &[
+                ("directory_id", self.directory_id.as_str()),
+                ("conflict", "version"),
+                ("file_name", file_name),
+                ("total_size", &content.len().to_string()),
+            ],
+        )
+        .map_err(|err| ServerFnError::new(format!("Failed to construct url: {err}")))?;
+
+        let response = CLIENT
+            .post(url)
+            .bearer_auth(&self.oauth_token)
+            .body(content)
+            .send()
+            .await
+            .map_err(reqwest_response_to_serverfn_err)?;
+
+        let json: UploadResponse = response.json().await?;
+
+        if &json.result == "success" {
+            Ok(())
+        } else {
+            Err(ServerFnError::new("Failed to upload file").into())
+        }
+    }
+
+    async fn download(&self, _file_name: &str) -> Result<Vec<u8>> {
+        let url = Url::parse(&format!(
+            "https://api.infomaniak.com/2/drive/{}/files/{}/download",
+            self.drive_id, self.file_id
+        ))
+        .map_err(|err| ServerFnError::new(format!("Failed to construct url: {err}")))?;
+
+        let response = CLIENT
+            .get(url)
+            .bearer_auth(&self.oauth_token)
+            .send()
+            .await
+            .map_err(reqwest_response_to_serverfn_err)?;
+
+        Ok(response.bytes().await?.to_vec())
+    }
+}
+
+/// A local directory, e.g. a mounted network share
+pub struct LocalDirectory {
+    path: PathBuf,
+}
+impl ExportBackend for LocalDirectory {
+    async fn upload(&self, file_name: &str, content: Vec<u8>) -> Result<()> {
+        tokio::fs::create_dir_all(&self.path).await.map_err(|err| {
+            ServerFnError::new(format!("Failed to create {}: {err}", self.path.display()))
+        })?;
+
+        // write to a temporary file first, so readers never see a partial export
+        let path = self.path.join(file_name);
+        let temporary = self.path.join(format!(".{file_name}.tmp"));
+        tokio::fs::write(&temporary, content).await.map_err(|err| {
+            ServerFnError::new(format!("Failed to write {}: {err}", temporary.display()))
+        })?;
+        tokio::fs::rename(&temporary, &path).await.map_err(|err| {
+            ServerFnError::new(format!("Failed to write {}: {err}", path.display())).into()
+        })
+    }
+
+    async fn download(&self, file_name: &str) -> Result<Vec<u8>> {
+        let path = self.path.join(file_name);
+        tokio::fs::read(&path).await.map_err(|err| {
+            ServerFnError::new(format!("Failed to read {}: {err}", path.display())).into()
+        })
+    }
+}
+
+/// A WebDAV collection, e.g. Nextcloud or a local stand-in for testing
+pub struct WebDav {
+    /// Ends with a slash
+    url: Url,
+    user: Option<String>,
+    password: Option<String>,
+}
+impl WebDav {
+    fn request(&self, method: reqwest::Method, file_name: &str) -> Result<reqwest::RequestBuilder> {
+        let url = self
+            .url
+            .join(file_name)
+            .map_err(|err| ServerFnError::new(format!("Failed to construct url: {err}")))?;
+
+        let request = CLIENT.request(method, url);
+        Ok(match &self.user {
+            Some(user) => request.basic_auth(user, self.password.as_ref()),
+            None => request,
+        })
+    }
+}
+impl ExportBackend for WebDav {
+    async fn upload(&self, file_name: &str, content: Vec<u8>) -> Result<()> {
+        self.request(reqwest::Method::PUT, file_name)?
+            .body(content)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|err| reqwest_response_to_serverfn_err(err).into())
+    }
+
+    async fn download(&self, file_name: &str) -> Result<Vec<u8>> {
+        let response = self
+            .request(reqwest::Method::GET, file_name)?
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(reqwest_response_to_serverfn_err)?;
+
+        Ok(response.bytes().await?.to_vec())
+    }
+}
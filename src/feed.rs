@@ -1,4 +1,4 @@
-use crate::components::fetch_editions;
+use crate::{components::fetch_editions, config::config};
 use atom_syndication::{EntryBuilder, FeedBuilder, FixedDateTime, LinkBuilder, Person};
 use dioxus::{
     fullstack::response::{IntoResponse, Response},
@@ -6,9 +6,8 @@ use dioxus::{
     server::http::header,
 };
 
-const URL: &str = "https://dieprobezeit.ch";
-
 #[get("/feed.xml")]
+#[tracing::instrument(err)]
 async fn atom_feed() -> Result<Response> {
     let author = Person {
         name: "Die Probe Zeit Team".into(),
@@ -44,7 +43,7 @@ async fn atom_feed() -> Result<Response> {
                 .id(model.id.to_string())
                 .link(
                     LinkBuilder::default()
-                        .href(format!("{URL}/editions/{}", model.id))
+                        .href(format!("{}/editions/{}", config().site_url, model.id))
                         .build(),
                 )
                 .updated(updated)
@@ -154,4 +154,276 @@ Translation! {
         DE: "Die Journalisten",
         CH: "D Journaliste",
         EN: "The Journalists")
+    analytics_title: (
+        DE: "Statistiken",
+        CH: "Statistike",
+        EN: "Analytics")
+    loading_analytics: (
+        DE: "Statistiken werden geladen...",
+        CH: "Statistike sind am lade...",
+        EN: "Loading analytics...")
+    error_loading_analytics: (
+        DE: "Fehler beim Laden der Statistiken",
+        CH: "Fehler bim Lade vo de Statistike",
+        EN: "Error loading analytics")
+    admin_token: (
+        DE: "Admin-Token",
+        CH: "Admin-Token",
+        EN: "Admin token")
+    log_in: (
+        DE: "Anmelden",
+        CH: "Aamälde",
+        EN: "Log in")
+    edition: (
+        DE: "Ausgabe",
+        CH: "Usgab",
+        EN: "Edition")
+    page: (
+        DE: "Seite",
+        CH: "Siite",
+        EN: "Page")
+    views: (
+        DE: "Aufrufe",
+        CH: "Ufrüef",
+        EN: "Views")
+    old_views: (
+        DE: "Alte Aufrufe",
+        CH: "Alti Ufrüef",
+        EN: "Old views")
+    read_time_minutes: (
+        DE: "Lesezeit (Minuten)",
+        CH: "Läsziit (Minute)",
+        EN: "Read time (minutes)")
+    unique_readers: (
+        DE: "Leser",
+        CH: "Läser",
+        EN: "Unique readers")
+    read_time_per_edition_chart: (
+        DE: "Lesezeit pro Ausgabe (Minuten)",
+        CH: "Läsziit pro Usgab (Minute)",
+        EN: "Read time per edition (minutes)")
+    readers_per_edition_chart: (
+        DE: "Leser pro Ausgabe",
+        CH: "Läser pro Usgab",
+        EN: "Unique readers per edition")
+    read_time_per_page_chart: (
+        DE: "Lesezeit pro Seite (Minuten)",
+        CH: "Läsziit pro Siite (Minute)",
+        EN: "Read time per page (minutes)")
+    readers_per_day_chart: (
+        DE: "Leser pro Tag",
+        CH: "Läser pro Tag",
+        EN: "Readers per day")
+    days_since_publication: (
+        DE: "Tage seit Veröffentlichung",
+        CH: "Täg sit de Veröffentlichung",
+        EN: "Days since publication")
+    read_time_distribution_chart: (
+        DE: "Verteilung der Lesezeiten auf Seite",
+        CH: "Verteilig vo de Läsziite uf Siite",
+        EN: "Distribution of read times on page")
+    heatmap_title: (
+        DE: "Aufmerksamkeit pro Seite",
+        CH: "Ufmerksamkeit pro Siite",
+        EN: "Attention per page")
+    median_read_time: (
+        DE: "Median-Lesezeit",
+        CH: "Median-Läsziit",
+        EN: "Median read time")
+    total_read_time: (
+        DE: "Gesamte Lesezeit",
+        CH: "Gsamti Läsziit",
+        EN: "Total read time")
+    data_retention: (
+        DE: "Datenaufbewahrung",
+        CH: "Dateufbewahrig",
+        EN: "Data retention")
+    no_retention_policy: (
+        DE: "Keine Aufbewahrungsfrist konfiguriert",
+        CH: "Kei Ufbewahrigsfrist konfiguriert",
+        EN: "No retention policy configured")
+    retention_cutoff: (
+        DE: "Betrifft Lesedaten vor",
+        CH: "Betrifft Läsdate vor",
+        EN: "Affects reads before")
+    affected_reads: (
+        DE: "Betroffene Lesedaten",
+        CH: "Betroffeni Läsdate",
+        EN: "Affected reads")
+    affected_readers: (
+        DE: "Betroffene Leser",
+        CH: "Betroffeni Läser",
+        EN: "Affected readers")
+    retention_delete: (
+        DE: "werden gelöscht",
+        CH: "werdet glöscht",
+        EN: "will be deleted")
+    retention_anonymize: (
+        DE: "werden anonymisiert",
+        CH: "werdet anonymisiert",
+        EN: "will be anonymized")
+    retention_dry_run: (
+        DE: "Testlauf, es wird nichts verändert",
+        CH: "Testlauf, es wird nüt veränderet",
+        EN: "Dry run, nothing is changed")
+    estimated_old_views: (
+        DE: "Geschätzte alte Aufrufe",
+        CH: "Gschätzti alti Ufrüef",
+        EN: "Estimated old views")
+    sources: (
+        DE: "Quellen",
+        CH: "Quelle",
+        EN: "Sources")
+    entry_point: (
+        DE: "Einstieg",
+        CH: "Iistieg",
+        EN: "Entry point")
+    referrer: (
+        DE: "Verweis",
+        CH: "Verwiis",
+        EN: "Referrer")
+    consent_text: (
+        DE: "Dürfen wir dein Gerät mit einer zufälligen ID wiedererkennen? So sehen wir, wie die Ausgaben gelesen werden. Ohne Zustimmung zählen wir nur anonym.",
+        CH: "Dörfed mir dis Gerät mit ere zuefällige ID wiedererkenne? So gsehnd mir, wie d Usgabe glese werdet. Ohni Zuestimmig zelled mir nur anonym.",
+        EN: "May we recognize your device with a random ID? This shows us how the editions are read. Without consent we only count anonymously.")
+    consent_accept: (
+        DE: "Zustimmen",
+        CH: "Zuestimme",
+        EN: "Accept")
+    consent_decline: (
+        DE: "Ablehnen",
+        CH: "Ablehne",
+        EN: "Decline")
+    consent_withdraw: (
+        DE: "Zustimmung widerrufen und Daten löschen",
+        CH: "Zuestimmig widerrüefe und Date lösche",
+        EN: "Withdraw consent and delete data")
+    no_consent: (
+        DE: "Du wirst nur anonym gezählt.",
+        CH: "Du wirsch nur anonym zellt.",
+        EN: "You are only counted anonymously.")
+    privacy_title: (
+        DE: "Datenschutz",
+        CH: "Dateschutz",
+        EN: "Privacy")
+    privacy_text: (
+        DE: "Hier siehst du, was wir über dein Gerät gespeichert haben, und kannst alles löschen.",
+        CH: "Da gsehsch, was mir über dis Gerät gspeicheret händ, und chasch alles lösche.",
+        EN: "Here you can see what we stored about your device and delete all of it.")
+    stored_data: (
+        DE: "Gespeicherte Daten",
+        CH: "Gspeichereti Date",
+        EN: "Stored data")
+    reads: (
+        DE: "Gelesene Seiten",
+        CH: "Gläseni Siite",
+        EN: "Read pages")
+    time: (
+        DE: "Zeitpunkt",
+        CH: "Ziitpunkt",
+        EN: "Time")
+    delete_stored_data: (
+        DE: "Alle Daten löschen",
+        CH: "Alli Date lösche",
+        EN: "Delete all data")
+    no_stored_data: (
+        DE: "Über dein Gerät ist nichts gespeichert.",
+        CH: "Über dis Gerät isch nüt gspeicheret.",
+        EN: "Nothing is stored about your device.")
+    loading_data: (
+        DE: "Daten werden geladen...",
+        CH: "Date sind am lade...",
+        EN: "Loading data...")
+    error_loading_data: (
+        DE: "Fehler beim Laden der Daten",
+        CH: "Fehler bim Lade vo de Date",
+        EN: "Error loading data")
+    error_deleting_data: (
+        DE: "Fehler beim Löschen der Daten",
+        CH: "Fehler bim Lösche vo de Date",
+        EN: "Error deleting data")
+    sessions: (
+        DE: "Sitzungen",
+        CH: "Sitzige",
+        EN: "Sessions")
+    average_session_length_minutes: (
+        DE: "Durchschnittliche Sitzungsdauer (Minuten)",
+        CH: "Durchschnittlichi Sitzigsduur (Minute)",
+        EN: "Average session length (minutes)")
+    pages_per_session: (
+        DE: "Seiten pro Sitzung",
+        CH: "Siite pro Sitzig",
+        EN: "Pages per session")
+    cohorts: (
+        DE: "Neue und wiederkehrende Leser",
+        CH: "Neui und wiederkehrendi Läser",
+        EN: "New and returning readers")
+    multi_edition_readers: (
+        DE: "Leser mehrerer Ausgaben",
+        CH: "Läser vo mehrere Usgabe",
+        EN: "Readers of multiple editions")
+    new_readers: (
+        DE: "Neue Leser",
+        CH: "Neui Läser",
+        EN: "New readers")
+    returning_readers: (
+        DE: "Wiederkehrende Leser",
+        CH: "Wiederkehrendi Läser",
+        EN: "Returning readers")
+    retained_from_previous: (
+        DE: "Von der vorherigen Ausgabe",
+        CH: "Vo de vorherige Usgab",
+        EN: "Retained from previous edition")
+    cohort_retained: (
+        DE: "Neue Leser, die wiederkamen",
+        CH: "Neui Läser, wo wieder cho sind",
+        EN: "New readers who returned")
+    editions_read: (
+        DE: "Gelesene Ausgaben",
+        CH: "Gläseni Usgabe",
+        EN: "Editions read")
+    export_status_title: (
+        DE: "Export-Status",
+        CH: "Export-Status",
+        EN: "Export status")
+    export_subject: (
+        DE: "Export",
+        CH: "Export",
+        EN: "Export")
+    export_state: (
+        DE: "Zustand",
+        CH: "Zuestand",
+        EN: "State")
+    export_ok: (
+        DE: "OK",
+        CH: "OK",
+        EN: "OK")
+    export_failing: (
+        DE: "Fehlgeschlagen",
+        CH: "Fählgschlage",
+        EN: "Failing")
+    export_pending: (
+        DE: "Noch nicht gelaufen",
+        CH: "No nöd gloffe",
+        EN: "Not run yet")
+    last_success: (
+        DE: "Letzter Erfolg",
+        CH: "Letschte Erfolg",
+        EN: "Last success")
+    last_failure: (
+        DE: "Letzter Fehler",
+        CH: "Letschte Fähler",
+        EN: "Last failure")
+    consecutive_failures: (
+        DE: "Fehler in Folge",
+        CH: "Fähler hinderenand",
+        EN: "Consecutive failures")
+    last_error: (
+        DE: "Fehlermeldung",
+        CH: "Fählermäldig",
+        EN: "Error message")
+    not_estimable: (
+        DE: "nicht schätzbar",
+        CH: "nöd schätzbar",
+        EN: "not estimable")
 }
@@ -0,0 +1,99 @@
+//! Log output and request ids.
+//!
+//! Logs are human readable, or JSON lines with `log_format = "json"` (see `config`). The level is
+//! set with `RUST_LOG` and defaults to `info`.
+//! Every request runs in a `request` span with its id, so errors logged by server functions (see
+//! `#[tracing::instrument(err)]`) can be correlated with the request that caused them. The id is
+//! taken from the `X-Request-Id` header if the reverse proxy sets one, and returned in the response.
+
+use dioxus::server::axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use serde::Deserialize;
+use tracing::{Instrument, Level, error, info_span, warn};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+/// Longest accepted request id from a proxy, longer ones are replaced
+const MAX_REQUEST_ID_LENGTH: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Pretty,
+    Json,
+}
+
+/// Install the global log subscriber. Has to run before dioxus installs its default one.
+pub fn init(format: LogFormat) {
+    let filter = EnvFilter::builder()
+        .with_default_directive(Level::INFO.into())
+        .from_env_lossy()
+        // hyper logs every connection on debug
+        .add_directive(
+            "hyper_util=warn"
+                .parse()
+                .expect("Directive should be valid"),
+        );
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    let result = match format {
+        LogFormat::Pretty => builder.try_init(),
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .try_init(),
+    };
+
+    if let Err(err) = result {
+        eprintln!("Failed to initialize logging: {err}");
+    }
+}
+
+/// Middleware running every request in a span with its request id
+pub async fn request_id(mut request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LENGTH
+                && id
+                    .chars()
+                    .all(|char| char.is_ascii_alphanumeric() || char == '-')
+        })
+        .map_or_else(|| Uuid::new_v4().to_string(), ToString::to_string);
+    let header_value = HeaderValue::from_str(&request_id).expect("Request id should be ASCII");
+    request
+        .headers_mut()
+        .insert(REQUEST_ID_HEADER.clone(), header_value.clone());
+
+    let span = info_span!(
+        "request",
+        request_id,
+        method = %request.method(),
+        path = request.uri().path(),
+    );
+
+    let mut response = next.run(request).instrument(span.clone()).await;
+
+    let status = response.status();
+    span.in_scope(|| {
+        if status.is_server_error() {
+            error!(status = status.as_u16(), "Request failed");
+        } else if status.is_client_error() && status != StatusCode::NOT_FOUND {
+            warn!(status = status.as_u16(), "Request rejected");
+        }
+    });
+
+    response
+        .headers_mut()
+        .insert(REQUEST_ID_HEADER.clone(), header_value);
+    response
+}
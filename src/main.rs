@@ -8,14 +8,41 @@ use views::*;
 mod components;
 mod views;
 
+mod analytics;
+mod attribution;
+#[cfg(feature = "server")]
+mod bots;
+#[cfg(feature = "server")]
+mod charts;
+mod cohorts;
+#[cfg(feature = "server")]
+mod config;
+mod consent;
 mod convert_pdf; // include here so rust-analyzer works for the script
 mod cookies;
 mod db;
 #[cfg(feature = "server")]
+mod estimate_views;
+#[cfg(feature = "server")]
+mod export;
+#[cfg(feature = "server")]
+mod export_backend;
+#[cfg(feature = "server")]
 mod feed;
 mod i18n;
 #[cfg(feature = "server")]
+mod logging;
+#[cfg(feature = "server")]
+mod metrics;
+#[cfg(feature = "server")]
+mod rate_limit;
+mod retention;
+#[cfg(feature = "server")]
+mod rollups;
+mod sessions;
+#[cfg(feature = "server")]
 mod sync_db;
+mod sync_status;
 mod track_views;
 
 /// The Route enum is used to define the structure of internal routes in our app. All route enums need to derive
@@ -39,6 +66,16 @@ enum Route {
         Feedback,
         #[route("/about")]
         About,
+        #[route("/privacy")]
+        Privacy,
+        #[route("/admin/analytics")]
+        Analytics {},
+        #[route("/admin/analytics/:id")]
+        EditionAnalytics { id: EditionId },
+        #[route("/admin/analytics/:id/heatmap")]
+        EditionHeatmap { id: EditionId },
+        #[route("/admin/exports")]
+        ExportStatus {},
 
     #[route("/editions/:id")]
     Edition { id: EditionId },
@@ -55,13 +92,26 @@ fn main() {
     #[cfg(not(feature = "server"))]
     dioxus::launch(App);
 
+    #[cfg(feature = "server")]
+    {
+        let config =
+            config::Config::load().unwrap_or_else(|err| panic!("Invalid configuration: {err}"));
+        logging::init(config.log_format);
+        config::init(config);
+    }
+
     #[cfg(feature = "server")]
     dioxus::serve(|| async move {
-        use crate::db::{Migrator, db, init_db};
+        use crate::{
+            config::config,
+            db::{Migrator, db, init_db},
+        };
+        use dioxus::server::axum::{middleware, routing::get};
         use sea_orm_migration::MigratorTrait;
         use std::time::Duration;
+        use tracing::{Instrument, info_span};
 
-        init_db().await;
+        init_db(&config().database_url).await;
         Migrator::up(db(), None)
             .await
             .expect("Failed to run migrations");
@@ -69,47 +119,120 @@ fn main() {
         let router = dioxus::server::router(App)
             .nest_service("/images", tower_http::services::ServeDir::new("images"))
             .nest_service("/svgs", tower_http::services::ServeDir::new("svgs"))
-            .nest_service("/pdfs", tower_http::services::ServeDir::new("pdfs"));
+            .nest_service("/pdfs", tower_http::services::ServeDir::new("pdfs"))
+            .route("/metrics", get(metrics::serve_metrics))
+            .route("/health", get(sync_status::serve_health))
+            .layer(middleware::from_fn(metrics::track_server_fns))
+            .layer(middleware::from_fn(logging::request_id));
 
-        // periodically sync feedback to kdrive
+        // periodically roll up reads into daily statistics, estimate old views from them and expire
+        // the rolled up raw reads
         tokio::spawn(async {
-            use crate::sync_db::sync_editions_to_kdrive;
-            use crate::sync_db::sync_feedback_to_kdrive;
+            use crate::estimate_views::estimate_old_views;
+            use crate::retention::enforce_retention;
+            use crate::rollups::roll_up_reads;
             use tokio::time::interval;
 
-            let mut interval = interval(Duration::from_mins(1));
+            let mut interval = interval(Duration::from_secs(config().rollup_interval));
 
             loop {
-                use crate::sync_db::{download_team_from_kdrive, sync_reads_to_kdrive};
-
                 interval.tick().await;
 
-                macro_rules! sync {
-                    ($($fn:ident | $subject:literal),*) => {
-                        $(
-                            let res = $fn().await;
-                            if let Err(e) = res {
-                                error!("Failed to sync {} to kdrive: {e}", $subject);
-                            }
+                async {
+                    if let Err(e) = roll_up_reads().await {
+                        error!(error = %e, "Failed to roll up reads");
+                        return;
+                    }
 
-                        )*
-                    };
-                }
-
-                sync!(
-                    sync_editions_to_kdrive | "editions",
-                    sync_feedback_to_kdrive | "feedback",
-                    sync_reads_to_kdrive | "reads"
-                );
+                    if let Err(e) = estimate_old_views().await {
+                        error!(error = %e, "Failed to estimate old views");
+                    }
 
-                let team = download_team_from_kdrive().await;
-                match team {
-                    Ok(team) => *TEAM.write().await = team,
-                    Err(e) => error!("Failed to download team from kdrive: {e}"),
+                    if let Err(e) = enforce_retention().await {
+                        error!(error = %e, "Failed to enforce read retention");
+                    }
                 }
+                .instrument(info_span!("rollup"))
+                .await;
             }
         });
 
+        // periodically export statistics and feedback, and download the team, each at its own
+        // interval. Failed runs are retried, see `sync_status`. Invalid export settings only
+        // disable this, not the site.
+        if let Err(err) = config().export.validate() {
+            error!(error = %err, "Invalid export settings, exports and the team download are disabled");
+            sync_status::record_disabled(&err);
+        } else {
+            use crate::sync_db::{
+                download_team, load_uploaded, sync_cohorts, sync_completion, sync_daily_reads,
+                sync_editions, sync_feedback, sync_pages, sync_reads, sync_sessions, sync_sources,
+            };
+            use crate::sync_status::with_retries;
+            use tokio::time::{Interval, MissedTickBehavior, interval};
+
+            // without them every file is uploaded again once
+            if let Err(e) = load_uploaded().await {
+                error!(error = %e, "Failed to load the hashes of the uploaded files");
+            }
+
+            // retries can take longer than the interval, skip the missed ticks then
+            let ticks_of = |subject| -> Interval {
+                let mut ticks = interval(config().export.interval_of(subject));
+                ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                ticks
+            };
+
+            macro_rules! export {
+                ($($fn:ident | $subject:literal),*) => {
+                    $(
+                        let mut ticks = ticks_of($subject);
+                        tokio::spawn(async move {
+                            loop {
+                                ticks.tick().await;
+
+                                let res = with_retries($subject, || {
+                                    metrics::track_export($subject, $fn())
+                                })
+                                .instrument(info_span!("export", subject = $subject))
+                                .await;
+                                if let Err(e) = res {
+                                    error!(error = %e, subject = $subject, "Failed to export");
+                                }
+                            }
+                        });
+                    )*
+                };
+            }
+
+            export!(
+                sync_editions | "editions",
+                sync_feedback | "feedback",
+                sync_reads | "reads",
+                sync_pages | "pages",
+                sync_daily_reads | "daily_reads",
+                sync_completion | "completion",
+                sync_sources | "sources",
+                sync_sessions | "sessions",
+                sync_cohorts | "cohorts"
+            );
+
+            let mut ticks = ticks_of("team");
+            tokio::spawn(async move {
+                loop {
+                    ticks.tick().await;
+
+                    let team = with_retries("team", download_team)
+                        .instrument(info_span!("download_team"))
+                        .await;
+                    match team {
+                        Ok(team) => *TEAM.write().await = team,
+                        Err(e) => error!(error = %e, "Failed to download team"),
+                    }
+                }
+            });
+        }
+
         Ok(router)
     });
 }
@@ -121,6 +244,7 @@ fn main() {
 #[component]
 fn App() -> Element {
     use_context_provider(|| Signal::new(i18n::get_lang()));
+    sessions::use_start_session();
 
     // The `rsx!` macro lets us define HTML inside of rust. It expands to an Element with all of our HTML inside.
     rsx! {
@@ -134,5 +258,6 @@ fn App() -> Element {
         // The router component renders the route enum we defined above. It will handle synchronization of the URL and render
         // the layouts and components for the active route.
         Router::<Route> {}
+        consent::ConsentBanner {}
     }
 }
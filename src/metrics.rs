@@ -0,0 +1,314 @@
+//! Prometheus metrics, served in the text exposition format on `/metrics`.
+//! Scrapes have to authenticate with the admin token (`admin_token`, see `config`) as bearer token.
+
+use crate::components::is_admin_token;
+use dioxus::server::axum::{
+    extract::Request,
+    http::{HeaderMap, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Write,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Upper bounds (seconds) of the database query latency buckets
+const QUERY_BUCKETS: &[f64] = &[0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0];
+/// Upper bounds (seconds) of the export duration buckets
+const EXPORT_BUCKETS: &[f64] = &[0.1, 0.5, 1.0, 5.0, 10.0, 30.0, 60.0];
+
+pub static SERVER_FN_CALLS: CounterVec = CounterVec::new(
+    "dieprobezeit_server_fn_calls_total",
+    "Server function calls",
+    &["path"],
+);
+pub static SERVER_FN_ERRORS: CounterVec = CounterVec::new(
+    "dieprobezeit_server_fn_errors_total",
+    "Server function calls that failed",
+    &["path"],
+);
+pub static VIEWS: CounterVec = CounterVec::new(
+    "dieprobezeit_views_total",
+    "Recorded views by outcome",
+    &["outcome"],
+);
+pub static READ_FLUSHES: CounterVec = CounterVec::new(
+    "dieprobezeit_read_flushes_total",
+    "Recorded read time flushes by outcome",
+    &["outcome"],
+);
+pub static EXPORTS: CounterVec = CounterVec::new(
+    "dieprobezeit_exports_total",
+    "Exports by subject and result",
+    &["subject", "result"],
+);
+pub static RETRIES: CounterVec = CounterVec::new(
+    "dieprobezeit_sync_retries_total",
+    "Retries of exports and the team download by subject",
+    &["subject"],
+);
+pub static EXPORT_DURATION: HistogramVec = HistogramVec::new(
+    "dieprobezeit_export_duration_seconds",
+    "Duration of exports",
+    &["subject"],
+    EXPORT_BUCKETS,
+);
+pub static DB_QUERY_DURATION: HistogramVec = HistogramVec::new(
+    "dieprobezeit_db_query_duration_seconds",
+    "Latency of database queries by statement kind",
+    &["kind", "result"],
+    QUERY_BUCKETS,
+);
+
+/// A counter per combination of label values
+pub struct CounterVec {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    values: Mutex<BTreeMap<Vec<String>, u64>>,
+}
+impl CounterVec {
+    const fn new(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Self {
+        Self {
+            name,
+            help,
+            labels,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Increment the counter of the label values, given in the order of the label names
+    pub fn inc(&self, label_values: &[&str]) {
+        let mut values = self.values.lock().unwrap_or_else(|err| err.into_inner());
+        *values
+            .entry(label_values.iter().map(ToString::to_string).collect())
+            .or_default() += 1;
+    }
+
+    fn render(&self, out: &mut String) {
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} counter", self.name);
+        let values = self.values.lock().unwrap_or_else(|err| err.into_inner());
+        for (label_values, value) in values.iter() {
+            let _ = writeln!(
+                out,
+                "{}{{{}}} {value}",
+                self.name,
+                labels(self.labels, label_values, None)
+            );
+        }
+    }
+}
+
+/// Bucket counts, sum and count of observations
+#[derive(Clone, Default)]
+struct Observations {
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+/// A histogram per combination of label values
+pub struct HistogramVec {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    buckets: &'static [f64],
+    values: Mutex<BTreeMap<Vec<String>, Observations>>,
+}
+impl HistogramVec {
+    const fn new(
+        name: &'static str,
+        help: &'static str,
+        labels: &'static [&'static str],
+        buckets: &'static [f64],
+    ) -> Self {
+        Self {
+            name,
+            help,
+            labels,
+            buckets,
+            values: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Observe a duration for the label values, given in the order of the label names
+    pub fn observe(&self, label_values: &[&str], duration: Duration) {
+        let seconds = duration.as_secs_f64();
+
+        let mut values = self.values.lock().unwrap_or_else(|err| err.into_inner());
+        let observations = values
+            .entry(label_values.iter().map(ToString::to_string).collect())
+            .or_insert_with(|| Observations {
+                buckets: vec![0; self.buckets.len()],
+                ..Default::default()
+            });
+
+        for (count, bound) in observations.buckets.iter_mut().zip(self.buckets) {
+            if seconds <= *bound {
+                *count += 1;
+            }
+        }
+        observations.sum += seconds;
+        observations.count += 1;
+    }
+
+    fn render(&self, out: &mut String) {
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} histogram", self.name);
+        let values = self.values.lock().unwrap_or_else(|err| err.into_inner());
+        for (label_values, observations) in values.iter() {
+            for (count, bound) in observations.buckets.iter().zip(self.buckets) {
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{{{}}} {count}",
+                    self.name,
+                    labels(self.labels, label_values, Some(&bound.to_string()))
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{}}} {}",
+                self.name,
+                labels(self.labels, label_values, Some("+Inf")),
+                observations.count
+            );
+
+            let labels = labels(self.labels, label_values, None);
+            let _ = writeln!(out, "{}_sum{{{labels}}} {}", self.name, observations.sum);
+            let _ = writeln!(
+                out,
+                "{}_count{{{labels}}} {}",
+                self.name, observations.count
+            );
+        }
+    }
+}
+
+/// Format label pairs, optionally with the `le` label of a histogram bucket
+fn labels(names: &[&str], values: &[String], le: Option<&str>) -> String {
+    names
+        .iter()
+        .zip(values)
+        .map(|(name, value)| (*name, value.as_str()))
+        .chain(le.map(|le| ("le", le)))
+        .map(|(name, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{name}=\"{value}\"")
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Record the latency of a database query, as `sea_orm`'s metric callback
+pub fn record_query(info: &sea_orm::metric::Info<'_>) {
+    let kind = info
+        .statement
+        .sql
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_uppercase();
+    let kind = match kind.as_str() {
+        "SELECT" | "INSERT" | "UPDATE" | "DELETE" => kind.as_str(),
+        _ => "OTHER",
+    };
+    let result = if info.failed { "error" } else { "ok" };
+
+    DB_QUERY_DURATION.observe(&[kind, result], info.elapsed);
+}
+
+/// Run an export and record its result and duration
+pub async fn track_export<T, E>(
+    subject: &str,
+    export: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let start = Instant::now();
+    let result = export.await;
+
+    EXPORT_DURATION.observe(&[subject], start.elapsed());
+    EXPORTS.inc(&[subject, if result.is_ok() { "success" } else { "failure" }]);
+
+    result
+}
+
+/// Paths of all registered server functions
+static SERVER_FN_PATHS: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
+    dioxus::server::ServerFunction::collect()
+        .into_iter()
+        .map(|server_fn| server_fn.path())
+        .collect()
+});
+
+/// Middleware counting server function calls and errors. Server functions are served under `/api`.
+/// Other `/api` paths are counted as `other`, so arbitrary paths can't make the label unbounded.
+pub async fn track_server_fns(request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    if !path.starts_with("/api/") {
+        return next.run(request).await;
+    }
+    let label = SERVER_FN_PATHS.get(path).copied().unwrap_or("other");
+
+    let response = next.run(request).await;
+
+    let status = response.status();
+    SERVER_FN_CALLS.inc(&[label]);
+    if status.is_client_error() || status.is_server_error() {
+        SERVER_FN_ERRORS.inc(&[label]);
+    }
+
+    response
+}
+
+/// Whether the request carries the admin token as bearer token
+fn authorized(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|authorization| authorization.to_str().ok())
+        .and_then(|authorization| authorization.strip_prefix("Bearer "))
+        .is_some_and(is_admin_token)
+}
+
+/// Serve all metrics, and the current edition count. Unauthorized scrapes get a 401.
+pub async fn serve_metrics(headers: HeaderMap) -> Response {
+    use crate::db::{db, entities::edition};
+    use sea_orm::{EntityTrait, PaginatorTrait};
+
+    if !authorized(&headers) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+        )
+            .into_response();
+    }
+
+    let mut out = String::new();
+    for counter in [
+        &SERVER_FN_CALLS,
+        &SERVER_FN_ERRORS,
+        &VIEWS,
+        &READ_FLUSHES,
+        &EXPORTS,
+        &RETRIES,
+    ] {
+        counter.render(&mut out);
+    }
+    for histogram in [&EXPORT_DURATION, &DB_QUERY_DURATION] {
+        histogram.render(&mut out);
+    }
+
+    // left out if counting fails, so scrapes don't fail along with the database
+    if let Ok(editions) = edition::Entity::find().count(db()).await {
+        let _ = writeln!(out, "# HELP dieprobezeit_editions Current edition count");
+        let _ = writeln!(out, "# TYPE dieprobezeit_editions gauge");
+        let _ = writeln!(out, "dieprobezeit_editions {editions}");
+    }
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], out).into_response()
+}
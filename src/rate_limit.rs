@@ -0,0 +1,75 @@
+//! In-memory fixed window rate limiting of tracking requests
+
+use dioxus::fullstack::FullstackContext;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Only prune expired windows once this many keys are tracked
+const PRUNE_THRESHOLD: usize = 1024;
+/// Keys tracked at most. Requests of new keys are rejected while this many keys have unexpired
+/// windows, so spoofed keys can't exhaust memory.
+const MAX_KEYS: usize = 100_000;
+
+/// Allows `limit` requests per key in every window of `window`
+pub struct RateLimiter {
+    limit: u32,
+    window: Duration,
+    /// (window start, requests in window) per key
+    hits: Mutex<HashMap<String, (Instant, u32)>>,
+}
+impl RateLimiter {
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            hits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count a request of `key`, returning whether it is within the limit
+    pub fn check(&self, key: String) -> bool {
+        let now = Instant::now();
+        let mut hits = self.hits.lock().unwrap_or_else(|err| err.into_inner());
+
+        if hits.len() >= PRUNE_THRESHOLD {
+            hits.retain(|_, (start, _)| now - *start < self.window);
+        }
+        if hits.len() >= MAX_KEYS && !hits.contains_key(&key) {
+            return false;
+        }
+
+        let (start, count) = hits.entry(key).or_insert((now, 0));
+        if now - *start >= self.window {
+            *start = now;
+            *count = 0;
+        }
+        *count += 1;
+
+        *count <= self.limit
+    }
+}
+
+/// The IP address of the client of the current request.
+/// Uses the last `X-Forwarded-For` address, which was appended by the reverse proxy (pingoo) the
+/// backend runs behind. Earlier addresses are sent by the client and can be spoofed.
+pub fn client_ip() -> Option<String> {
+    FullstackContext::current().and_then(|context| {
+        let parts = context.parts_mut();
+        parts
+            .headers
+            .get("x-forwarded-for")
+            .and_then(|forwarded| forwarded.to_str().ok())
+            .and_then(|forwarded| forwarded.rsplit(',').next())
+            .or_else(|| {
+                parts
+                    .headers
+                    .get("x-real-ip")
+                    .and_then(|real_ip| real_ip.to_str().ok())
+            })
+            .map(|ip| ip.trim().to_string())
+            .filter(|ip| !ip.is_empty())
+    })
+}
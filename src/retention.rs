@@ -0,0 +1,192 @@
+//! Retention policy for the raw read event log.
+//!
+//! Raw reads older than `retention.days` days are deleted or anonymized (client id set to `NO_ID`),
+//! depending on `retention.mode` (`delete` or `anonymize`, see `config`).
+//! Only reads that are already final in the daily rollups are affected.
+//! The day each client first read a page (`first_reads`) is kept, so new readers are still counted
+//! correctly in later rollups.
+//! With `retention.dry_run = true` the policy is only reported, not applied.
+
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+use time::PrimitiveDateTime;
+
+#[cfg(feature = "server")]
+use crate::{
+    config::config,
+    db::{
+        db,
+        entities::{daily_edition_reads, reads},
+    },
+    track_views::NO_ID,
+};
+#[cfg(feature = "server")]
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QuerySelect, prelude::Expr};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionMode {
+    Delete,
+    #[default]
+    Anonymize,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub days: u32,
+    #[serde(default)]
+    pub mode: RetentionMode,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RetentionReport {
+    pub policy: RetentionPolicy,
+    /// Reads before this are affected
+    pub cutoff: PrimitiveDateTime,
+    pub affected_reads: i64,
+    /// Distinct (non-anonymous) clients among the affected reads
+    pub affected_clients: i64,
+}
+
+#[cfg(feature = "server")]
+impl RetentionPolicy {
+    /// Anonymize reads older than `days`
+    pub const fn with_days(days: u32) -> Self {
+        Self {
+            days,
+            mode: RetentionMode::Anonymize,
+            dry_run: false,
+        }
+    }
+
+    /// Reads before the returned timestamp fall under the policy.
+    /// None if nothing is rolled up yet.
+    async fn cutoff(&self) -> Result<Option<PrimitiveDateTime>, ServerFnError> {
+        use time::{Duration, Time, UtcDateTime};
+
+        let last_day = daily_edition_reads::Entity::find()
+            .select_only()
+            .expr(daily_edition_reads::Column::Day.max())
+            .into_tuple::<Option<time::Date>>()
+            .one(db())
+            .await
+            .map_err(|err| ServerFnError::new(format!("Failed to get last rolled up day: {err}")))?
+            .flatten();
+        // the rollups of the last two days are still recomputed, see `roll_up_reads`
+        let Some(rolled_up) =
+            last_day.map(|day| PrimitiveDateTime::new(day - Duration::days(1), Time::MIDNIGHT))
+        else {
+            return Ok(None);
+        };
+
+        let expired = UtcDateTime::now() - Duration::days(self.days.into());
+        let expired = PrimitiveDateTime::new(expired.date(), expired.time());
+
+        Ok(Some(expired.min(rolled_up)))
+    }
+
+    /// Report what applying the policy would affect
+    pub async fn report(self) -> Result<Option<RetentionReport>, ServerFnError> {
+        let Some(cutoff) = self.cutoff().await? else {
+            return Ok(None);
+        };
+
+        let mut affected = reads::Entity::find()
+            .select_only()
+            .filter(reads::Column::Timestamp.lt(cutoff));
+        if self.mode == RetentionMode::Anonymize {
+            affected = affected.filter(reads::Column::ClientId.ne(NO_ID));
+        }
+
+        let (affected_reads, affected_clients): (i64, i64) = affected
+            .expr(Expr::col(reads::Column::Id).count())
+            .expr(
+                Expr::expr(Expr::case(
+                    reads::Column::ClientId.ne(NO_ID),
+                    Expr::col(reads::Column::ClientId),
+                ))
+                .count_distinct(),
+            )
+            .into_tuple()
+            .one(db())
+            .await
+            .map_err(|err| ServerFnError::new(format!("Failed to count expired reads: {err}")))?
+            .unwrap_or_default();
+
+        Ok(Some(RetentionReport {
+            policy: self,
+            cutoff,
+            affected_reads,
+            affected_clients,
+        }))
+    }
+
+    /// Delete or anonymize expired reads, returning the number of affected reads
+    pub async fn apply(&self) -> Result<u64, ServerFnError> {
+        let Some(cutoff) = self.cutoff().await? else {
+            return Ok(0);
+        };
+
+        let result = match self.mode {
+            RetentionMode::Delete => reads::Entity::delete_many()
+                .filter(reads::Column::Timestamp.lt(cutoff))
+                .exec(db())
+                .await
+                .map(|result| result.rows_affected),
+            RetentionMode::Anonymize => reads::Entity::update_many()
+                .col_expr(reads::Column::ClientId, Expr::value(NO_ID))
+                .filter(reads::Column::Timestamp.lt(cutoff))
+                .filter(reads::Column::ClientId.ne(NO_ID))
+                .exec(db())
+                .await
+                .map(|result| result.rows_affected),
+        };
+
+        result.map_err(|err| ServerFnError::new(format!("Failed to apply retention policy: {err}")))
+    }
+}
+
+/// Apply the configured retention policy, or only log what it would affect on a dry run
+#[cfg(feature = "server")]
+pub async fn enforce_retention() -> Result<()> {
+    let Some(policy) = config().retention.clone() else {
+        return Ok(());
+    };
+
+    if policy.dry_run {
+        if let Some(report) = policy.report().await? {
+            info!(
+                "Read retention dry run: {} reads of {} clients before {} would be {}",
+                report.affected_reads,
+                report.affected_clients,
+                report.cutoff,
+                match report.policy.mode {
+                    RetentionMode::Delete => "deleted",
+                    RetentionMode::Anonymize => "anonymized",
+                }
+            );
+        }
+        return Ok(());
+    }
+
+    let affected = policy.apply().await?;
+    if affected > 0 {
+        info!("Read retention: {affected} reads affected");
+    }
+    Ok(())
+}
+
+/// Report what the configured retention policy affects. None if none is configured or nothing is
+/// rolled up yet.
+#[server]
+#[tracing::instrument(skip_all, err)]
+pub async fn fetch_retention_report() -> Result<Option<RetentionReport>, ServerFnError> {
+    crate::components::require_admin().await?;
+
+    match config().retention.clone() {
+        Some(policy) => policy.report().await,
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,254 @@
+//! Aggregate the raw read event log into daily rollups, so statistics don't have to scan `reads`
+
+use crate::{
+    components::EditionId,
+    db::{
+        db,
+        entities::{daily_edition_reads, daily_page_reads, first_reads, reads},
+    },
+    track_views::NO_ID,
+};
+use dioxus::prelude::*;
+use sea_orm::{
+    ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set, TransactionTrait,
+    sea_query::OnConflict,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use time::{Date, Duration, PrimitiveDateTime, Time};
+use uuid::Uuid;
+
+/// Reads of an edition by the same client (or anonymous session) that start within this duration
+/// of each other belong to the same session
+const SESSION_GAP: Duration = Duration::minutes(30);
+
+#[derive(Default)]
+struct Rollup {
+    /// Anonymous reads (`NO_ID`) are not counted as readers
+    readers: HashSet<Uuid>,
+    /// Readers that never read this edition (or page) before
+    new_readers: i32,
+    /// Readers that never read any edition before
+    first_time_readers: i32,
+    read_time: f32,
+    sessions: i32,
+}
+
+/// Recompute the rollups from the day before the last rolled up day on.
+/// These days are recomputed as reads can still be merged into after the day they started on, also
+/// across midnight.
+/// Whether a reader is new is decided by `first_reads`, not by the raw reads, which may already be
+/// deleted or anonymized by the retention policy.
+pub async fn roll_up_reads() -> Result<()> {
+    let db = db();
+
+    let last_day: Option<Date> = daily_edition_reads::Entity::find()
+        .select_only()
+        .expr(daily_edition_reads::Column::Day.max())
+        .into_tuple::<Option<Date>>()
+        .one(db)
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to get last rolled up day: {err}")))?
+        .flatten();
+
+    let from_day = match last_day {
+        Some(last_day) => last_day - Duration::days(1),
+        None => {
+            let first_read: Option<PrimitiveDateTime> = reads::Entity::find()
+                .select_only()
+                .expr(reads::Column::Timestamp.min())
+                .into_tuple::<Option<PrimitiveDateTime>>()
+                .one(db)
+                .await
+                .map_err(|err| {
+                    ServerFnError::new(format!("Failed to get first read timestamp: {err}"))
+                })?
+                .flatten();
+            match first_read {
+                Some(first_read) => first_read.date(),
+                None => return Ok(()),
+            }
+        }
+    };
+    let from = PrimitiveDateTime::new(from_day, Time::MIDNIGHT);
+
+    let window_reads = reads::Entity::find()
+        .filter(reads::Column::Timestamp.gte(from))
+        .order_by_asc(reads::Column::Timestamp)
+        .all(db)
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to get reads to roll up: {err}")))?;
+
+    let clients = window_reads
+        .iter()
+        .map(|read| read.client_id)
+        .filter(|client_id| *client_id != NO_ID)
+        .collect::<HashSet<_>>();
+
+    // what the clients of this window already read before it
+    let earlier_reads: Vec<(Uuid, EditionId, i32)> = first_reads::Entity::find()
+        .select_only()
+        .column(first_reads::Column::ClientId)
+        .column(first_reads::Column::EditionId)
+        .column(first_reads::Column::PageNumber)
+        .filter(first_reads::Column::Day.lt(from_day))
+        .filter(first_reads::Column::ClientId.is_in(clients))
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to get earlier reads: {err}")))?;
+
+    let mut seen_pages = earlier_reads.iter().copied().collect::<HashSet<_>>();
+    let mut seen_editions = earlier_reads
+        .iter()
+        .map(|(client_id, edition_id, _)| (*client_id, *edition_id))
+        .collect::<HashSet<_>>();
+    let mut seen_clients = earlier_reads
+        .iter()
+        .map(|(client_id, _, _)| *client_id)
+        .collect::<HashSet<_>>();
+    let mut last_read = HashMap::<(Uuid, EditionId), PrimitiveDateTime>::new();
+    let mut new_first_reads = Vec::new();
+
+    let mut pages = BTreeMap::<(Date, EditionId, i32), Rollup>::new();
+    let mut editions = BTreeMap::<(Date, EditionId), Rollup>::new();
+
+    // reads are sorted by timestamp, so the first occurence of a client is its earliest read
+    for read in window_reads {
+        let day = read.timestamp.date();
+        let page = pages
+            .entry((day, read.edition_id, read.page_number))
+            .or_default();
+        let edition = editions.entry((day, read.edition_id)).or_default();
+
+        page.read_time += read.read_time;
+        page.sessions += 1;
+        edition.read_time += read.read_time;
+
+        // anonymous reads can only be told apart by their session, older ones not at all
+        let reader = if read.client_id != NO_ID {
+            Some(read.client_id)
+        } else {
+            read.session_id
+        };
+        let previous =
+            reader.and_then(|reader| last_read.insert((reader, read.edition_id), read.timestamp));
+        if previous.is_none_or(|previous| {
+            previous.date() != day || read.timestamp - previous > SESSION_GAP
+        }) {
+            edition.sessions += 1;
+        }
+
+        if read.client_id == NO_ID {
+            continue;
+        }
+        page.readers.insert(read.client_id);
+        edition.readers.insert(read.client_id);
+        if seen_pages.insert((read.client_id, read.edition_id, read.page_number)) {
+            page.new_readers += 1;
+            new_first_reads.push(first_reads::ActiveModel {
+                client_id: Set(read.client_id),
+                edition_id: Set(read.edition_id),
+                page_number: Set(read.page_number),
+                day: Set(day),
+            });
+        }
+        if seen_editions.insert((read.client_id, read.edition_id)) {
+            edition.new_readers += 1;
+        }
+        if seen_clients.insert(read.client_id) {
+            edition.first_time_readers += 1;
+        }
+    }
+
+    let page_models = pages
+        .into_iter()
+        .map(
+            |((day, edition_id, page_number), rollup)| daily_page_reads::ActiveModel {
+                day: Set(day),
+                edition_id: Set(edition_id),
+                page_number: Set(page_number),
+                readers: Set(rollup.readers.len() as i32),
+                new_readers: Set(rollup.new_readers),
+                read_time: Set(rollup.read_time),
+                sessions: Set(rollup.sessions),
+                ..Default::default()
+            },
+        )
+        .collect::<Vec<_>>();
+    let edition_models = editions
+        .into_iter()
+        .map(
+            |((day, edition_id), rollup)| daily_edition_reads::ActiveModel {
+                day: Set(day),
+                edition_id: Set(edition_id),
+                readers: Set(rollup.readers.len() as i32),
+                new_readers: Set(rollup.new_readers),
+                first_time_readers: Set(rollup.first_time_readers),
+                read_time: Set(rollup.read_time),
+                sessions: Set(rollup.sessions),
+                ..Default::default()
+            },
+        )
+        .collect::<Vec<_>>();
+
+    let txn = db
+        .begin()
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to start transaction: {err}")))?;
+
+    daily_page_reads::Entity::delete_many()
+        .filter(daily_page_reads::Column::Day.gte(from_day))
+        .exec(&txn)
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to delete daily page reads: {err}")))?;
+    daily_edition_reads::Entity::delete_many()
+        .filter(daily_edition_reads::Column::Day.gte(from_day))
+        .exec(&txn)
+        .await
+        .map_err(|err| {
+            ServerFnError::new(format!("Failed to delete daily edition reads: {err}"))
+        })?;
+
+    first_reads::Entity::delete_many()
+        .filter(first_reads::Column::Day.gte(from_day))
+        .exec(&txn)
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to delete first reads: {err}")))?;
+
+    if !new_first_reads.is_empty() {
+        first_reads::Entity::insert_many(new_first_reads)
+            .on_conflict(
+                OnConflict::columns([
+                    first_reads::Column::ClientId,
+                    first_reads::Column::EditionId,
+                    first_reads::Column::PageNumber,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .do_nothing()
+            .exec(&txn)
+            .await
+            .map_err(|err| ServerFnError::new(format!("Failed to insert first reads: {err}")))?;
+    }
+    if !page_models.is_empty() {
+        daily_page_reads::Entity::insert_many(page_models)
+            .exec(&txn)
+            .await
+            .map_err(|err| {
+                ServerFnError::new(format!("Failed to insert daily page reads: {err}"))
+            })?;
+    }
+    if !edition_models.is_empty() {
+        daily_edition_reads::Entity::insert_many(edition_models)
+            .exec(&txn)
+            .await
+            .map_err(|err| {
+                ServerFnError::new(format!("Failed to insert daily edition reads: {err}"))
+            })?;
+    }
+
+    txn.commit()
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to commit rollups: {err}")).into())
+}
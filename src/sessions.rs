@@ -0,0 +1,259 @@
+//! Reading sessions: one per page load, which every read flush of the page load references.
+//! A session records when it started and last flushed, the device class, the path the reader
+//! landed on and the editions read during it.
+
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[cfg(feature = "server")]
+use crate::{
+    components::EditionId,
+    db::{
+        db,
+        entities::{reads, session_editions, sessions},
+    },
+    rate_limit::RateLimiter,
+    track_views::NO_ID,
+};
+#[cfg(feature = "server")]
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QuerySelect, QueryTrait, Set,
+    prelude::Expr,
+    sea_query::{OnConflict, SimpleExpr},
+};
+#[cfg(feature = "server")]
+use std::{collections::BTreeMap, sync::LazyLock, time::Duration as StdDuration};
+#[cfg(feature = "server")]
+use time::PrimitiveDateTime;
+
+/// Longest stored entry path, longer ones are truncated
+#[cfg(feature = "server")]
+const MAX_ENTRY_LENGTH: usize = 200;
+
+/// Sessions started per IP and minute. Every page load starts one.
+#[cfg(feature = "server")]
+static SESSION_LIMIT: LazyLock<RateLimiter> =
+    LazyLock::new(|| RateLimiter::new(120, StdDuration::from_secs(60)));
+
+/// The session of this page load
+pub static SESSION_ID: GlobalSignal<Uuid> = Signal::global(Uuid::new_v4);
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum DeviceClass {
+    Mobile,
+    Tablet,
+    Desktop,
+}
+impl DeviceClass {
+    #[cfg(feature = "server")]
+    pub const fn to_str(self) -> &'static str {
+        match self {
+            Self::Mobile => "mobile",
+            Self::Tablet => "tablet",
+            Self::Desktop => "desktop",
+        }
+    }
+
+    /// Classify by viewport width and touch support
+    #[cfg(feature = "web")]
+    fn detect() -> Self {
+        use web_sys::window;
+
+        let Some(window) = window() else {
+            return Self::Desktop;
+        };
+        let width = window
+            .inner_width()
+            .ok()
+            .and_then(|width| width.as_f64())
+            .unwrap_or_default();
+        let touch = window.navigator().max_touch_points() > 0;
+
+        match width {
+            ..768. => Self::Mobile,
+            ..1280. if touch => Self::Tablet,
+            _ => Self::Desktop,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionStats {
+    /// Sessions in which the edition was read
+    pub sessions: i64,
+    /// Average duration of these sessions in milliseconds, from start to the last flush
+    pub average_length: f64,
+    /// Average number of distinct pages of the edition read per session
+    pub pages_per_session: f64,
+}
+
+/// Start the session of this page load, once the app is mounted on the client
+pub fn use_start_session() {
+    use_effect(move || {
+        #[cfg(feature = "web")]
+        {
+            let device_class = DeviceClass::detect();
+            let entry = web_sys::window()
+                .and_then(|window| window.location().pathname().ok())
+                .unwrap_or_default();
+
+            spawn(async move {
+                let _ = start_session(SESSION_ID(), device_class, entry).await;
+            });
+        }
+    });
+}
+
+/// Record the start of a session. Sessions are only started once, bots are ignored.
+#[server]
+#[tracing::instrument(skip(session_id), err)]
+pub async fn start_session(
+    session_id: Uuid,
+    device_class: DeviceClass,
+    entry: String,
+) -> Result<(), ServerFnError> {
+    use crate::{bots, rate_limit, track_views::tracked_client_id};
+
+    if rate_limit::client_ip().is_some_and(|ip| !SESSION_LIMIT.check(ip)) {
+        return Err(ServerFnError::new("Too many sessions"));
+    }
+
+    let tracked = tracked_client_id();
+    let user_agent = bots::user_agent();
+    if bots::classify_view(user_agent.as_deref(), tracked).is_some() {
+        return Ok(());
+    }
+
+    let session = sessions::ActiveModel {
+        session_id: Set(session_id),
+        client_id: Set(tracked.unwrap_or(NO_ID)),
+        device_class: Set(device_class.to_str().to_string()),
+        entry: Set(entry.chars().take(MAX_ENTRY_LENGTH).collect()),
+        ..Default::default()
+    };
+
+    sessions::Entity::insert(session)
+        .on_conflict(
+            OnConflict::column(sessions::Column::SessionId)
+                .do_nothing()
+                .to_owned(),
+        )
+        .do_nothing()
+        .exec(db())
+        .await
+        .map(|_| ())
+        .map_err(|err| ServerFnError::new(format!("Failed to start session: {err}")))
+}
+
+/// Extend a session to `now`, add the edition to the editions read in it and update the distinct
+/// pages of the edition read in it from the reads. Unknown sessions are ignored.
+#[cfg(feature = "server")]
+pub async fn touch_session(
+    db: &impl ConnectionTrait,
+    session_id: Uuid,
+    edition_id: EditionId,
+    now: PrimitiveDateTime,
+) -> Result<(), DbErr> {
+    let updated = sessions::Entity::update_many()
+        .col_expr(sessions::Column::End, Expr::value(now))
+        .filter(sessions::Column::SessionId.eq(session_id))
+        .exec(db)
+        .await?;
+    if updated.rows_affected == 0 {
+        return Ok(());
+    }
+
+    session_editions::Entity::insert(session_editions::ActiveModel {
+        session_id: Set(session_id),
+        edition_id: Set(edition_id),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::columns([
+            session_editions::Column::SessionId,
+            session_editions::Column::EditionId,
+        ])
+        .do_nothing()
+        .to_owned(),
+    )
+    .do_nothing()
+    .exec(db)
+    .await?;
+
+    let pages = reads::Entity::find()
+        .select_only()
+        .expr(Expr::col(reads::Column::PageNumber).count_distinct())
+        .filter(reads::Column::SessionId.eq(session_id))
+        .filter(reads::Column::EditionId.eq(edition_id))
+        .into_query();
+    session_editions::Entity::update_many()
+        .col_expr(
+            session_editions::Column::Pages,
+            SimpleExpr::SubQuery(None, Box::new(pages.into_sub_query_statement())),
+        )
+        .filter(session_editions::Column::SessionId.eq(session_id))
+        .filter(session_editions::Column::EditionId.eq(edition_id))
+        .exec(db)
+        .await
+        .map(|_| ())
+}
+
+/// Sessions, average session length and distinct pages read per session of every edition read in a
+/// session, or only of `edition_id`
+#[cfg(feature = "server")]
+pub async fn sessions_per_edition(
+    edition_id: Option<EditionId>,
+) -> Result<BTreeMap<EditionId, SessionStats>, ServerFnError> {
+    use sea_orm::{
+        JoinType, RelationTrait,
+        sea_query::{Alias, Func},
+    };
+
+    /// Milliseconds per day, as `JULIANDAY` returns days
+    const DAY_MILLIS: i64 = 86_400_000;
+    let julian_day =
+        |column| Func::cust(Alias::new("JULIANDAY")).arg(Expr::col((sessions::Entity, column)));
+    let length = Expr::expr(julian_day(sessions::Column::End))
+        .sub(julian_day(sessions::Column::Start))
+        .mul(DAY_MILLIS);
+
+    // (edition id, sessions, average length in milliseconds, average pages)
+    let sessions: Vec<(EditionId, i64, Option<f64>, Option<f64>)> =
+        session_editions::Entity::find()
+            .select_only()
+            .column(session_editions::Column::EditionId)
+            .expr(session_editions::Column::SessionId.count())
+            .expr(Func::avg(length))
+            .expr(Func::avg(Expr::col(session_editions::Column::Pages)))
+            .join(
+                JoinType::InnerJoin,
+                session_editions::Relation::Sessions.def(),
+            )
+            .apply_if(edition_id, |query, edition_id| {
+                query.filter(session_editions::Column::EditionId.eq(edition_id))
+            })
+            .group_by(session_editions::Column::EditionId)
+            .into_tuple()
+            .all(db())
+            .await
+            .map_err(|err| {
+                ServerFnError::new(format!("Failed to get sessions per edition: {err}"))
+            })?;
+
+    Ok(sessions
+        .into_iter()
+        .map(
+            |(edition_id, sessions, average_length, pages_per_session)| {
+                (
+                    edition_id,
+                    SessionStats {
+                        sessions,
+                        average_length: average_length.unwrap_or_default(),
+                        pages_per_session: pages_per_session.unwrap_or_default(),
+                    },
+                )
+            },
+        )
+        .collect())
+}
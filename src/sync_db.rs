@@ -1,187 +1,600 @@
-use std::{iter, sync::LazyLock};
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{LazyLock, Mutex},
+};
 
 use crate::{
+    analytics::read_totals_per_edition,
+    attribution::sources_per_edition,
+    bots::{self, flagged_per_edition},
+    cohorts::cohort_report,
+    components::{EditionId, count_pages},
+    config::config,
     db::{
         db,
-        entities::{edition, feedback, reads},
+        entities::{
+            daily_edition_reads, daily_page_reads, edition, feedback, read_progress, uploaded_files,
+        },
     },
+    export::Export,
+    export_backend::{BACKEND, ExportBackend},
+    sessions::sessions_per_edition,
     views::Team,
 };
-use dioxus::{
-    fullstack::{
-        reqwest::{self, Url},
-        reqwest_response_to_serverfn_err,
-    },
-    prelude::*,
-};
-use sea_orm::{EntityTrait, QuerySelect, prelude::*};
-use serde::Deserialize;
-use time::Duration;
+use dioxus::prelude::*;
+use sea_orm::{EntityTrait, QueryOrder, QuerySelect, prelude::*};
+use serde::Serialize;
+use time::Date;
+
+/// Hash of the last uploaded content per file, persisted in `uploaded_files`
+static UPLOADED: LazyLock<Mutex<HashMap<String, u64>>> = LazyLock::new(Default::default);
+
+#[derive(Default, Serialize)]
+struct ReadsRow {
+    #[serde(rename = "Edition ID")]
+    edition_id: EditionId,
+    #[serde(rename = "Total Read Time (s)")]
+    total_read_time: f64,
+    #[serde(rename = "Unique Readers")]
+    unique_readers: i64,
+    #[serde(rename = "Flagged Read Time (s)")]
+    flagged_read_time: f64,
+    #[serde(rename = "Raw Read Time (s)")]
+    raw_read_time: f64,
+}
+
+#[derive(Default, Serialize)]
+struct PageRow {
+    #[serde(rename = "Edition ID")]
+    edition_id: EditionId,
+    #[serde(rename = "Page")]
+    page: i32,
+    #[serde(rename = "Read Time (s)")]
+    read_time: f64,
+    #[serde(rename = "Unique Readers")]
+    unique_readers: i64,
+    #[serde(rename = "Reads")]
+    reads: i64,
+    #[serde(rename = "Read Time per Reader (s)")]
+    read_time_per_reader: Option<f64>,
+}
+
+#[derive(Default, Serialize)]
+struct DailyReadsRow {
+    #[serde(rename = "Edition ID")]
+    edition_id: EditionId,
+    #[serde(rename = "Date")]
+    date: String,
+    #[serde(rename = "Days Since Publication")]
+    days_since_publication: i64,
+    #[serde(rename = "Readers")]
+    readers: i32,
+    #[serde(rename = "New Readers")]
+    new_readers: i32,
+    #[serde(rename = "First Time Readers")]
+    first_time_readers: i32,
+    #[serde(rename = "Read Time (s)")]
+    read_time: f64,
+    #[serde(rename = "Sessions")]
+    sessions: i32,
+}
+
+#[derive(Default, Serialize)]
+struct CompletionRow {
+    #[serde(rename = "Edition ID")]
+    edition_id: EditionId,
+    #[serde(rename = "Sessions")]
+    sessions: usize,
+    #[serde(rename = "Finished Sessions")]
+    finished_sessions: usize,
+    #[serde(rename = "Completion Rate")]
+    completion_rate: f64,
+}
+
+#[derive(Default, Serialize)]
+struct DropOffRow {
+    #[serde(rename = "Edition ID")]
+    edition_id: EditionId,
+    #[serde(rename = "Page")]
+    page: i32,
+    #[serde(rename = "Reached")]
+    reached: usize,
+    #[serde(rename = "Dropped Off")]
+    dropped_off: usize,
+    #[serde(rename = "Reach Rate")]
+    reach_rate: f64,
+}
+
+#[derive(Default, Serialize)]
+struct SourceRow {
+    #[serde(rename = "Edition ID")]
+    edition_id: EditionId,
+    #[serde(rename = "Entry")]
+    entry: String,
+    #[serde(rename = "Referrer")]
+    referrer: Option<String>,
+    #[serde(rename = "UTM Source")]
+    utm_source: Option<String>,
+    #[serde(rename = "UTM Medium")]
+    utm_medium: Option<String>,
+    #[serde(rename = "UTM Campaign")]
+    utm_campaign: Option<String>,
+    #[serde(rename = "Readers")]
+    readers: i64,
+    #[serde(rename = "Read Time (s)")]
+    read_time: f64,
+}
 
-static CLIENT: LazyLock<reqwest::Client> = LazyLock::new(reqwest::Client::new);
+#[derive(Default, Serialize)]
+struct SessionRow {
+    #[serde(rename = "Edition ID")]
+    edition_id: EditionId,
+    #[serde(rename = "Sessions")]
+    sessions: i64,
+    #[serde(rename = "Average Session Length (s)")]
+    average_length: f64,
+    #[serde(rename = "Pages per Session")]
+    pages_per_session: f64,
+}
 
-#[derive(Deserialize)]
-struct UploadResponse {
-    result: String,
+#[derive(Default, Serialize)]
+struct CohortRow {
+    #[serde(rename = "Edition ID")]
+    edition_id: EditionId,
+    #[serde(rename = "Date")]
+    date: String,
+    #[serde(rename = "Readers")]
+    readers: i64,
+    #[serde(rename = "New Readers")]
+    new_readers: i64,
+    #[serde(rename = "Returning Readers")]
+    returning_readers: i64,
+    #[serde(rename = "Retained From Previous")]
+    retained_from_previous: Option<i64>,
+    #[serde(rename = "Retention Rate")]
+    retention_rate: Option<f64>,
+    #[serde(rename = "Cohort Retained")]
+    cohort_retained: i64,
 }
 
-pub async fn sync_reads_to_kdrive() -> Result<()> {
+#[derive(Default, Serialize)]
+struct EditionsPerReaderRow {
+    #[serde(rename = "Editions Read")]
+    editions_read: usize,
+    #[serde(rename = "Readers")]
+    readers: i64,
+}
+
+#[derive(Default, Serialize)]
+struct FeedbackRow {
+    #[serde(rename = "Feedback")]
+    content: String,
+    #[serde(rename = "E-Mail")]
+    email: Option<String>,
+}
+
+#[derive(Default, Serialize)]
+struct EditionRow {
+    #[serde(rename = "Date")]
+    date: String,
+    #[serde(rename = "Title")]
+    title: Option<String>,
+    #[serde(rename = "Views")]
+    views: i32,
+    #[serde(rename = "Flagged Views")]
+    flagged_views: i64,
+    #[serde(rename = "Raw Views")]
+    raw_views: i64,
+    #[serde(rename = "Old Views")]
+    old_views: i32,
+    #[serde(rename = "Estimated Old Views")]
+    estimated_old_views: Option<i32>,
+    #[serde(rename = "Hidden")]
+    hidden: bool,
+}
+
+/// Milliseconds to seconds
+fn seconds(milliseconds: f64) -> f64 {
+    milliseconds / 1000.
+}
+
+pub async fn sync_reads() -> Result<()> {
     let db = db();
 
     // (edition id, total read time, unique readers)
-    let per_edition: Vec<(i32, f32, i64)> = reads::Entity::find()
+    let per_edition = read_totals_per_edition().await?;
+
+    let total_unique_readers: Option<i64> = daily_edition_reads::Entity::find()
         .select_only()
-        .column(reads::Column::EditionId)
-        .expr(reads::Column::ReadTime.sum())
-        .expr(Expr::col(reads::Column::ClientId).count_distinct())
-        .group_by(reads::Column::EditionId)
-        .into_tuple()
-        .all(db)
+        .expr(daily_edition_reads::Column::FirstTimeReaders.sum())
+        .into_tuple::<Option<i64>>()
+        .one(db)
         .await
         .map_err(|err| {
-            ServerFnError::new(format!(
-                "Failed to get total read times and unique reader counts per edition: {err}"
-            ))
-        })?;
+            ServerFnError::new(format!("Failed to get total unique reader count: {err}"))
+        })?
+        .flatten();
+
+    // edition id -> (total read time, unique readers, flagged read time)
+    let mut per_edition = per_edition
+        .into_iter()
+        .map(|(edition_id, read_time, readers)| (edition_id, (read_time, readers, 0.0)))
+        .collect::<BTreeMap<_, _>>();
+    for (edition_id, _, flagged_read_time) in flagged_per_edition(bots::Kind::Read).await? {
+        per_edition.entry(edition_id).or_insert((0.0, 0, 0.0)).2 = flagged_read_time as f32;
+    }
+
+    let rows = per_edition.into_iter().map(
+        |(edition_id, (total_read_time, unique_readers, flagged_read_time))| ReadsRow {
+            edition_id,
+            total_read_time: seconds(total_read_time.into()),
+            unique_readers,
+            flagged_read_time: seconds(flagged_read_time.into()),
+            raw_read_time: seconds(f64::from(total_read_time) + f64::from(flagged_read_time)),
+        },
+    );
 
-    let total_unique_readers: Option<i64> = reads::Entity::find()
+    let export = Export::new("reads").table("reads", rows)?.summary(
+        "Total Unique Readers",
+        total_unique_readers.unwrap_or_default(),
+    );
+    upload_export(&export).await
+}
+
+/// Uploads the read time and readers of every page of every edition, from the daily rollups
+pub async fn sync_pages() -> Result<()> {
+    // (edition id, page number, read time, unique readers, reads)
+    let pages: Vec<(EditionId, i32, f64, i64, i64)> = daily_page_reads::Entity::find()
         .select_only()
-        .expr(Expr::col(reads::Column::ClientId).count_distinct())
+        .column(daily_page_reads::Column::EditionId)
+        .column(daily_page_reads::Column::PageNumber)
+        .expr(daily_page_reads::Column::ReadTime.sum())
+        .expr(daily_page_reads::Column::NewReaders.sum())
+        .expr(daily_page_reads::Column::Sessions.sum())
+        .group_by(daily_page_reads::Column::EditionId)
+        .group_by(daily_page_reads::Column::PageNumber)
+        .order_by_asc(daily_page_reads::Column::EditionId)
+        .order_by_asc(daily_page_reads::Column::PageNumber)
         .into_tuple()
-        .one(db)
+        .all(db())
         .await
-        .map_err(|err| {
-            ServerFnError::new(format!("Failed to get total unique reader count: {err}"))
-        })?;
-
-    let csv =
-        iter::once("Edition ID,Total Read Time,Unique Readers,,Total Unique Readers\n".to_string())
-            .chain(per_edition.iter().enumerate().map(
-                |(i, (edition_id, total_read_time, unique_readers))| {
-                    format!(
-                        "{},{},{},,{}\n",
-                        edition_id,
-                        Duration::milliseconds(*total_read_time as i64),
-                        unique_readers,
-                        if i == 0 {
-                            total_unique_readers.unwrap_or_default().to_string()
-                        } else {
-                            String::new()
-                        },
-                    )
-                },
-            ))
-            .collect::<String>();
-
-    upload_to_kdrive("reads.csv", csv).await
-}
-
-pub async fn sync_feedback_to_kdrive() -> Result<()> {
-    kdrive_sync_table::<feedback::Entity>("feedback", "Feedback,E-Mail", |feedback| {
-        format!(
-            "{},{}\n",
-            csv_str(feedback.content),
-            csv_str(feedback.email.unwrap_or_default())
-        )
-    })
-    .await
+        .map_err(|err| ServerFnError::new(format!("Failed to get read times per page: {err}")))?;
+
+    let rows =
+        pages.into_iter().map(
+            |(edition_id, page, read_time, unique_readers, reads)| PageRow {
+                edition_id,
+                page: page + 1,
+                read_time: seconds(read_time),
+                unique_readers,
+                reads,
+                read_time_per_reader: (unique_readers > 0)
+                    .then(|| seconds(read_time) / unique_readers as f64),
+            },
+        );
+
+    upload_export(&Export::new("pages").table("pages", rows)?).await
 }
 
-pub async fn sync_editions_to_kdrive() -> Result<()> {
-    kdrive_sync_table::<edition::Entity>("edition", "Date,Title,Views,OldViews,Hidden", |edition| {
-        format!(
-            "{},{},{},{},{}\n",
-            edition.date,
-            csv_str(edition.title.unwrap_or_default()),
-            edition.views,
-            edition.old_views,
-            edition.hidden,
-        )
-    })
-    .await
+/// Uploads the readers and read time of every edition on every day, from its publication (or first
+/// read) to the last rolled up day. Days without reads are included, so the series has no gaps.
+pub async fn sync_daily_reads() -> Result<()> {
+    let db = db();
+
+    let days = daily_edition_reads::Entity::find()
+        .order_by_asc(daily_edition_reads::Column::EditionId)
+        .order_by_asc(daily_edition_reads::Column::Day)
+        .all(db)
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to get daily reads: {err}")))?;
+    // None if nothing is rolled up yet
+    let last_day = days.iter().map(|day| day.day).max();
+
+    let mut per_edition = BTreeMap::<EditionId, BTreeMap<Date, daily_edition_reads::Model>>::new();
+    for day in days {
+        per_edition
+            .entry(day.edition_id)
+            .or_default()
+            .insert(day.day, day);
+    }
+
+    let editions = edition::Entity::find()
+        .filter(edition::Column::Id.is_in(per_edition.keys().copied()))
+        .order_by_asc(edition::Column::Id)
+        .all(db)
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to get editions: {err}")))?;
+
+    let mut rows = Vec::new();
+    for edition in editions {
+        let days = &per_edition[&edition.id];
+        let first_read = days.keys().next().copied().unwrap_or(edition.date);
+
+        let mut day = edition.date.min(first_read);
+        while last_day.is_some_and(|last_day| day <= last_day) {
+            let reads = days.get(&day);
+            rows.push(DailyReadsRow {
+                edition_id: edition.id,
+                date: day.to_string(),
+                days_since_publication: (day - edition.date).whole_days(),
+                readers: reads.map_or(0, |reads| reads.readers),
+                new_readers: reads.map_or(0, |reads| reads.new_readers),
+                first_time_readers: reads.map_or(0, |reads| reads.first_time_readers),
+                read_time: reads.map_or(0., |reads| seconds(reads.read_time.into())),
+                sessions: reads.map_or(0, |reads| reads.sessions),
+            });
+
+            let Some(next) = day.next_day() else {
+                break;
+            };
+            day = next;
+        }
+    }
+
+    upload_export(&Export::new("daily_reads").table_with_dates("daily_reads", &["Date"], rows)?)
+        .await
 }
 
-async fn kdrive_sync_table<Entity: EntityTrait>(
-    entity_name: &str,
-    columns: &str,
-    format_entity: impl Fn(Entity::Model) -> String,
-) -> Result<()> {
-    let entities = Entity::find().all(db()).await.map_err(|err| {
-        ServerFnError::new(format!("Failed to get all {entity_name} entities: {err}"))
-    })?;
+/// Uploads the completion rate of every edition and how many reading sessions reached and dropped
+/// off at each page
+pub async fn sync_completion() -> Result<()> {
+    let db = db();
 
-    let csv = std::iter::once(format!("{columns}\n"))
-        .chain(entities.into_iter().map(format_entity))
-        .collect::<String>();
+    // (edition id, furthest page, finished)
+    let progress: Vec<(EditionId, i32, bool)> = read_progress::Entity::find()
+        .select_only()
+        .column(read_progress::Column::EditionId)
+        .column(read_progress::Column::FurthestPage)
+        .column(read_progress::Column::Finished)
+        .into_tuple()
+        .all(db)
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to get read progress: {err}")))?;
 
-    upload_to_kdrive(&format!("{entity_name}.csv"), csv).await
+    let mut per_edition = BTreeMap::<EditionId, Vec<(i32, bool)>>::new();
+    for (edition_id, furthest_page, finished) in progress {
+        per_edition
+            .entry(edition_id)
+            .or_default()
+            .push((furthest_page, finished));
+    }
+
+    let editions = edition::Entity::find()
+        .filter(edition::Column::Id.is_in(per_edition.keys().copied()))
+        .all(db)
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to get editions: {err}")))?;
+
+    let mut completion = Vec::new();
+    let mut drop_off = Vec::new();
+    for edition in editions {
+        let sessions = &per_edition[&edition.id];
+        let finished = sessions.iter().filter(|(_, finished)| *finished).count();
+        completion.push(CompletionRow {
+            edition_id: edition.id,
+            sessions: sessions.len(),
+            finished_sessions: finished,
+            completion_rate: finished as f64 / sessions.len() as f64,
+        });
+
+        for page in 0..count_pages(&edition).await? as i32 {
+            let reached = sessions
+                .iter()
+                .filter(|(furthest_page, _)| *furthest_page >= page)
+                .count();
+            let dropped_off = sessions
+                .iter()
+                .filter(|(furthest_page, finished)| *furthest_page == page && !finished)
+                .count();
+            drop_off.push(DropOffRow {
+                edition_id: edition.id,
+                page: page + 1,
+                reached,
+                dropped_off,
+                reach_rate: reached as f64 / sessions.len() as f64,
+            });
+        }
+    }
+
+    let export = Export::new("completion")
+        .table("completion", completion)?
+        .table("drop_off", drop_off)?;
+    upload_export(&export).await
+}
+
+/// Uploads the readers and read time of every edition per entry point, referrer and campaign
+pub async fn sync_sources() -> Result<()> {
+    let rows = sources_per_edition()
+        .await?
+        .into_iter()
+        .flat_map(|(edition_id, sources)| {
+            sources.into_iter().map(move |source| SourceRow {
+                edition_id,
+                entry: source.entry,
+                referrer: source.referrer,
+                utm_source: source.campaign.source,
+                utm_medium: source.campaign.medium,
+                utm_campaign: source.campaign.campaign,
+                readers: source.readers,
+                read_time: seconds(source.read_time as f64),
+            })
+        });
+
+    upload_export(&Export::new("sources").table("sources", rows)?).await
 }
 
-/// Escape rust String to a csv string
-fn csv_str(string: String) -> String {
-    format!("\"{}\"", string.replace('"', "\"\""))
+/// Uploads the sessions, average session length and pages per session of every edition
+pub async fn sync_sessions() -> Result<()> {
+    let rows = sessions_per_edition(None)
+        .await?
+        .into_iter()
+        .map(|(edition_id, stats)| SessionRow {
+            edition_id,
+            sessions: stats.sessions,
+            average_length: seconds(stats.average_length),
+            pages_per_session: stats.pages_per_session,
+        });
+
+    upload_export(&Export::new("sessions").table("sessions", rows)?).await
 }
 
-pub async fn download_team_from_kdrive() -> Result<Team> {
-    let drive_id = read_kdrive_config("drive-id").await?;
-    let oauth_token = read_kdrive_config("oauth-token").await?;
-    let file_id = read_kdrive_config("file-id").await?;
+/// Uploads new, returning and retained readers of every edition, and how many readers read how
+/// many editions
+pub async fn sync_cohorts() -> Result<()> {
+    let report = cohort_report().await?;
 
-    let url = Url::parse(&format!(
-        "https://api.infomaniak.com/2/drive/{}/files/{}/download",
-        drive_id.trim(),
-        file_id.trim()
-    ))
-    .map_err(|err| ServerFnError::new(format!("Failed to construct url: {err}")))?;
+    let cohorts = report.editions.into_iter().map(|cohort| CohortRow {
+        edition_id: cohort.edition.id,
+        date: cohort.edition.date.to_string(),
+        readers: cohort.readers,
+        new_readers: cohort.new_readers,
+        returning_readers: cohort.returning_readers,
+        retained_from_previous: cohort.retained_from_previous,
+        retention_rate: cohort.retention_rate,
+        cohort_retained: cohort.cohort_retained,
+    });
+    let editions_per_reader =
+        report
+            .editions_per_reader
+            .into_iter()
+            .map(|(editions_read, readers)| EditionsPerReaderRow {
+                editions_read,
+                readers,
+            });
 
-    let response = CLIENT
-        .get(url)
-        .bearer_auth(oauth_token.trim())
-        .send()
+    let export = Export::new("cohorts")
+        .table_with_dates("cohorts", &["Date"], cohorts)?
+        .table("editions_per_reader", editions_per_reader)?
+        .summary("Readers", report.readers)
+        .summary("Multi Edition Readers", report.multi_edition_readers);
+    upload_export(&export).await
+}
+
+pub async fn sync_feedback() -> Result<()> {
+    let feedback = feedback::Entity::find()
+        .all(db())
         .await
-        .map_err(reqwest_response_to_serverfn_err)?;
+        .map_err(|err| ServerFnError::new(format!("Failed to get all feedback: {err}")))?;
 
-    Ok(response.json().await?)
+    let rows = feedback.into_iter().map(|feedback| FeedbackRow {
+        content: feedback.content,
+        email: feedback.email,
+    });
+
+    upload_export(&Export::new("feedback").table("feedback", rows)?).await
 }
 
-async fn read_kdrive_config(file_name: &str) -> Result<String> {
-    tokio::fs::read_to_string(format!("kdrive/{file_name}"))
+pub async fn sync_editions() -> Result<()> {
+    let flagged_views = flagged_per_edition(bots::Kind::View)
+        .await?
+        .into_iter()
+        .map(|(edition_id, flagged_views, _)| (edition_id, flagged_views))
+        .collect::<HashMap<_, _>>();
+
+    let editions = edition::Entity::find()
+        .all(db())
         .await
-        .map_err(|err| ServerFnError::new(format!("Failed to read {file_name}: {err}")).into())
-}
-
-async fn upload_to_kdrive(file_name: &str, content: String) -> Result<()> {
-    let drive_id = read_kdrive_config("drive-id").await?;
-    let oauth_token = read_kdrive_config("oauth-token").await?;
-    let directory_id = read_kdrive_config("directory-id").await?;
-
-    let url = Url::parse_with_params(
-        &format!(
-            "https://api.infomaniak.com/3/drive/{}/upload",
-            drive_id.trim()
-        ),
-        &[
-            ("directory_id", directory_id.trim()),
-            ("conflict", "version"),
-            ("file_name", file_name),
-            ("total_size", &content.len().to_string()),
-        ],
-    )
-    .map_err(|err| ServerFnError::new(format!("Failed to construct url: {err}")))?;
+        .map_err(|err| ServerFnError::new(format!("Failed to get all editions: {err}")))?;
+
+    let rows = editions.into_iter().map(|edition| {
+        let flagged_views = flagged_views.get(&edition.id).copied().unwrap_or_default();
+        EditionRow {
+            date: edition.date.to_string(),
+            title: edition.title,
+            views: edition.views,
+            flagged_views,
+            raw_views: edition.views as i64 + flagged_views,
+            old_views: edition.old_views,
+            estimated_old_views: edition.estimated_old_views,
+            hidden: edition.hidden,
+        }
+    });
 
-    let response = CLIENT
-        .post(url)
-        .bearer_auth(oauth_token.trim())
-        .body(content)
-        .send()
+    // the table keeps the name of the former edition.csv
+    upload_export(&Export::new("editions").table_with_dates("edition", &["Date"], rows)?).await
+}
+
+/// File name of the team in the export backend
+const TEAM_FILE: &str = "team.json";
+
+pub async fn download_team() -> Result<Team> {
+    let team = BACKEND.download(TEAM_FILE).await?;
+    serde_json::from_slice(&team)
+        .map_err(|err| ServerFnError::new(format!("Failed to parse team: {err}")).into())
+}
+
+/// Upload the files of an export in every configured format
+async fn upload_export(export: &Export) -> Result<()> {
+    for format in &config().export.formats {
+        for (file_name, content) in export.files(*format)? {
+            upload(&file_name, content).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Load the hashes of the last uploaded files, so unchanged files aren't uploaded again after a
+/// restart
+pub async fn load_uploaded() -> Result<()> {
+    let files: Vec<(String, i64)> = uploaded_files::Entity::find()
+        .select_only()
+        .column(uploaded_files::Column::FileName)
+        .column(uploaded_files::Column::Hash)
+        .into_tuple()
+        .all(db())
         .await
-        .map_err(reqwest_response_to_serverfn_err)?;
+        .map_err(|err| ServerFnError::new(format!("Failed to get uploaded files: {err}")))?;
+
+    UPLOADED
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .extend(
+            files
+                .into_iter()
+                .map(|(file_name, hash)| (file_name, hash as u64)),
+        );
+    Ok(())
+}
+
+/// Upload a file, unless it is unchanged since its last upload.
+/// Unchanged files would otherwise create a new version in the backend every time.
+/// The hash isn't guaranteed to be stable across Rust versions, which at worst uploads every file
+/// once more.
+async fn upload(file_name: &str, content: Vec<u8>) -> Result<()> {
+    use sea_orm::{Set, sea_query::OnConflict};
 
-    let json: UploadResponse = response.json().await?;
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    let hash = hasher.finish();
 
-    if &json.result == "success" {
-        Ok(())
-    } else {
-        Err(ServerFnError::new("Failed to upload file").into())
+    let uploaded = |uploaded: &HashMap<String, u64>| uploaded.get(file_name) == Some(&hash);
+    if uploaded(&UPLOADED.lock().unwrap_or_else(|err| err.into_inner())) {
+        return Ok(());
     }
+
+    BACKEND.upload(file_name, content).await?;
+
+    uploaded_files::Entity::insert(uploaded_files::ActiveModel {
+        file_name: Set(file_name.to_string()),
+        hash: Set(hash as i64),
+        ..Default::default()
+    })
+    .on_conflict(
+        OnConflict::column(uploaded_files::Column::FileName)
+            .update_columns([
+                uploaded_files::Column::Hash,
+                uploaded_files::Column::Timestamp,
+            ])
+            .to_owned(),
+    )
+    .exec(db())
+    .await
+    .map_err(|err| ServerFnError::new(format!("Failed to store hash of {file_name}: {err}")))?;
+
+    UPLOADED
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+        .insert(file_name.to_string(), hash);
+    Ok(())
 }
@@ -0,0 +1,206 @@
+//! Retries and status of the exports and the team download.
+//!
+//! Failed runs are retried with exponential backoff and jitter. The last success and failure of
+//! every subject are kept in memory, shown to admins on `/admin/exports` and served on `/health`.
+
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+use time::PrimitiveDateTime;
+
+#[cfg(feature = "server")]
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+
+/// Attempts of a single run, including the first one
+#[cfg(feature = "server")]
+const MAX_ATTEMPTS: u32 = 4;
+/// Delay before the first retry, doubled for every further one
+#[cfg(feature = "server")]
+const BASE_DELAY: Duration = Duration::from_secs(2);
+#[cfg(feature = "server")]
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Status of every subject that ran at least once
+#[cfg(feature = "server")]
+static STATUS: LazyLock<Mutex<BTreeMap<&'static str, SubjectStatus>>> =
+    LazyLock::new(Default::default);
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SubjectStatus {
+    /// An export subject or `team`
+    pub subject: String,
+    /// UTC
+    pub last_success: Option<PrimitiveDateTime>,
+    /// UTC
+    pub last_failure: Option<PrimitiveDateTime>,
+    pub last_error: Option<String>,
+    /// Failed runs since the last success, each after all retries
+    pub consecutive_failures: u32,
+}
+impl SubjectStatus {
+    /// Whether the last run failed, so the exported files (or the team) are stale
+    pub const fn failing(&self) -> bool {
+        self.consecutive_failures > 0
+    }
+}
+
+/// Run `run`, retrying it on failure, and record the outcome as the status of the subject
+#[cfg(feature = "server")]
+pub async fn with_retries<T, E: Display, F: Future<Output = Result<T, E>>>(
+    subject: &'static str,
+    mut run: impl FnMut() -> F,
+) -> Result<T, E> {
+    let mut attempt = 1;
+    loop {
+        match run().await {
+            Ok(value) => {
+                record(subject, None);
+                return Ok(value);
+            }
+            Err(err) if attempt < MAX_ATTEMPTS => {
+                let delay = backoff(attempt);
+                tracing::warn!(error = %err, subject, attempt, ?delay, "Retrying");
+                crate::metrics::RETRIES.inc(&[subject]);
+
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(err) => {
+                record(subject, Some(err.to_string()));
+                return Err(err);
+            }
+        }
+    }
+}
+
+/// Delay before retrying after the failed attempt: exponential, capped at `MAX_DELAY`, and randomly
+/// shortened by up to half, so retries of different subjects don't hit the backend at once
+#[cfg(feature = "server")]
+fn backoff(attempt: u32) -> Duration {
+    let delay = BASE_DELAY
+        .saturating_mul(1 << (attempt - 1).min(16))
+        .min(MAX_DELAY);
+    delay.mul_f64(rand::random_range(0.5..=1.0))
+}
+
+/// Record a successful run, or a failed one with its error
+#[cfg(feature = "server")]
+fn record(subject: &'static str, error: Option<String>) {
+    use time::UtcDateTime;
+
+    let now = UtcDateTime::now();
+    let now = PrimitiveDateTime::new(now.date(), now.time().truncate_to_second());
+
+    let mut status = STATUS.lock().unwrap_or_else(|err| err.into_inner());
+    let status = status.entry(subject).or_insert_with(|| SubjectStatus {
+        subject: subject.to_string(),
+        ..Default::default()
+    });
+    match error {
+        None => {
+            status.last_success = Some(now);
+            status.consecutive_failures = 0;
+        }
+        Some(error) => {
+            status.last_failure = Some(now);
+            status.last_error = Some(error);
+            status.consecutive_failures += 1;
+        }
+    }
+}
+
+/// Record every subject as failed, if they can't run at all because of the export settings
+#[cfg(feature = "server")]
+pub fn record_disabled(error: &str) {
+    for subject in crate::config::SUBJECTS {
+        record(subject, Some(error.to_string()));
+    }
+}
+
+/// The status of every subject, in the order of `SUBJECTS`. Subjects that didn't run yet have
+/// neither a success nor a failure.
+#[cfg(feature = "server")]
+pub fn statuses() -> Vec<SubjectStatus> {
+    use crate::config::SUBJECTS;
+
+    let status = STATUS.lock().unwrap_or_else(|err| err.into_inner());
+    SUBJECTS
+        .iter()
+        .map(|subject| {
+            status
+                .get(subject)
+                .cloned()
+                .unwrap_or_else(|| SubjectStatus {
+                    subject: subject.to_string(),
+                    ..Default::default()
+                })
+        })
+        .collect()
+}
+
+#[server]
+#[tracing::instrument(skip_all, err)]
+pub async fn fetch_sync_status() -> Result<Vec<SubjectStatus>, ServerFnError> {
+    crate::components::require_admin().await?;
+    Ok(statuses())
+}
+
+/// Serve the health as JSON: `ok`, `degraded` if an export or the team download is failing, or
+/// `down` (with status 503) if the database is unreachable. Errors are only shown to admins.
+#[cfg(feature = "server")]
+pub async fn serve_health() -> impl dioxus::server::axum::response::IntoResponse {
+    use crate::db::db;
+    use dioxus::server::axum::{Json, http::StatusCode};
+
+    #[derive(Serialize)]
+    struct Subject {
+        subject: String,
+        last_success: Option<String>,
+        last_failure: Option<String>,
+        failing: bool,
+    }
+
+    #[derive(Serialize)]
+    struct Health {
+        status: &'static str,
+        database: bool,
+        subjects: Vec<Subject>,
+    }
+
+    let database = db().ping().await.is_ok();
+    let subjects = statuses()
+        .into_iter()
+        .map(|status| Subject {
+            failing: status.failing(),
+            subject: status.subject,
+            last_success: status.last_success.map(|time| time.to_string()),
+            last_failure: status.last_failure.map(|time| time.to_string()),
+        })
+        .collect::<Vec<_>>();
+
+    let status = if !database {
+        "down"
+    } else if subjects.iter().any(|subject| subject.failing) {
+        "degraded"
+    } else {
+        "ok"
+    };
+    let code = if database {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        code,
+        Json(Health {
+            status,
+            database,
+            subjects,
+        }),
+    )
+}
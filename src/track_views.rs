@@ -1,16 +1,31 @@
 use crate::{
     components::EditionId,
-    cookies::{get_cookie, get_or_insert_cookie},
+    consent::tracking_allowed,
+    cookies::{delete_cookie, get_cookie, get_or_insert_cookie},
 };
 use dioxus::prelude::*;
+#[cfg(feature = "server")]
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+    ColumnTrait, DbErr, EntityTrait, QueryFilter, QuerySelect, Set, TransactionTrait,
     prelude::Expr,
+    sea_query::{Alias, OnConflict},
 };
 use std::str::FromStr;
 use time::{Duration, PrimitiveDateTime, UtcDateTime};
 use uuid::Uuid;
 
+#[cfg(feature = "web")]
+use serde::Serialize;
+
+#[cfg(feature = "server")]
+use crate::{rate_limit::RateLimiter, sessions::touch_session};
+#[cfg(feature = "server")]
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{LazyLock, Mutex},
+    time::{Duration as StdDuration, Instant},
+};
+
 pub const NO_ID: Uuid = Uuid::nil();
 
 /// A Client UUID used for view deduplication
@@ -29,7 +44,11 @@ impl ClientId {
 }
 
 const STORAGE_KEY: &str = "client_id";
+/// Set the client id cookie, if the reader consented to tracking
 pub fn ensure_client_id_set() {
+    if !tracking_allowed() {
+        return;
+    }
     get_or_insert_cookie(
         STORAGE_KEY,
         || ClientId::new().to_string(),
@@ -39,92 +58,979 @@ pub fn ensure_client_id_set() {
 pub fn get_client_id() -> Option<ClientId> {
     get_cookie(STORAGE_KEY, ClientId::from_str)
 }
+pub fn delete_client_id() {
+    delete_cookie(STORAGE_KEY);
+}
+
+/// The client id to record views and reads with. None if the reader didn't consent to tracking or
+/// sends a privacy signal, in which case they are recorded anonymously.
+/// Consenting clients without a client id cookie are `NO_ID`.
+#[cfg(feature = "server")]
+pub fn tracked_client_id() -> Option<Uuid> {
+    tracking_allowed().then(|| get_client_id().map_or(NO_ID, |client_id| client_id.0))
+}
+
+/// Anonymous views per (visitor, edition, day), so they are only counted once per visitor and day.
+/// Only kept in memory, so anonymous readers aren't stored in the database.
+#[cfg(feature = "server")]
+static ANONYMOUS_VIEWS: LazyLock<Mutex<HashSet<(u64, EditionId, time::Date)>>> =
+    LazyLock::new(Default::default);
+/// Anonymous views remembered at most, the oldest days are forgotten first
+#[cfg(feature = "server")]
+const MAX_ANONYMOUS_VIEWS: usize = 100_000;
+/// Random salt of the anonymous visitor hashes and the day it is used on. Replaced every day, so
+/// visitors can't be followed across days, and never stored.
+#[cfg(feature = "server")]
+static VISITOR_SALT: LazyLock<Mutex<Option<(time::Date, u128)>>> = LazyLock::new(Default::default);
+/// Views per IP and minute. As high as the session limit, as a whole school can share an IP.
+#[cfg(feature = "server")]
+static VIEW_LIMIT: LazyLock<RateLimiter> =
+    LazyLock::new(|| RateLimiter::new(120, StdDuration::from_secs(60)));
+
+/// An anonymous visitor on a day: a hash of the IP and User-Agent, salted with the salt of the day.
+/// Unlike the session id, it survives reloads and new tabs and isn't chosen by the client.
+#[cfg(feature = "server")]
+fn anonymous_visitor(day: time::Date, ip: Option<&str>, user_agent: Option<&str>) -> u64 {
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    let mut salt = VISITOR_SALT.lock().unwrap_or_else(|err| err.into_inner());
+    let salt = match *salt {
+        Some((salt_day, salt)) if salt_day == day => salt,
+        _ => salt.insert((day, rand::random())).1,
+    };
+
+    let mut hasher = DefaultHasher::new();
+    (salt, ip, user_agent).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Remember an anonymous view, returning whether it is the first of the visitor, edition and day
+#[cfg(feature = "server")]
+fn first_anonymous_view(visitor: u64, edition_id: EditionId, day: time::Date) -> bool {
+    let mut views = ANONYMOUS_VIEWS
+        .lock()
+        .unwrap_or_else(|err| err.into_inner());
+
+    if views.len() >= MAX_ANONYMOUS_VIEWS {
+        views.retain(|(_, _, viewed)| *viewed == day);
+        if views.len() >= MAX_ANONYMOUS_VIEWS {
+            views.clear();
+        }
+    }
+
+    views.insert((visitor, edition_id, day))
+}
+
+/// Record a view of an edition. Views of the same client are only counted once per edition and
+/// day, so reloading or calling this repeatedly doesn't inflate `edition.views`.
+/// Anonymous views are counted once per IP and User-Agent, edition and day.
+#[server]
+#[tracing::instrument(err)]
+pub async fn record_view(edition_id: EditionId) -> Result<(), ServerFnError> {
+    use crate::{
+        bots,
+        db::{
+            db,
+            entities::{edition, views},
+        },
+        metrics::VIEWS,
+        rate_limit,
+    };
+    use sea_orm::TryInsertResult;
+
+    let ip = rate_limit::client_ip();
+    if ip.as_ref().is_some_and(|ip| !VIEW_LIMIT.check(ip.clone())) {
+        VIEWS.inc(&["rejected"]);
+        return Err(ServerFnError::new("Too many views"));
+    }
+
+    if edition::Entity::find_by_id(edition_id)
+        .one(db())
+        .await
+        .map_err(|err| ServerFnError::new(err.to_string()))?
+        .is_none()
+    {
+        VIEWS.inc(&["rejected"]);
+        return Err(ServerFnError::new(format!(
+            "Edition {edition_id} not found"
+        )));
+    }
+
+    let tracked = tracked_client_id();
+    let client_id = tracked.unwrap_or(NO_ID);
+
+    let user_agent = bots::user_agent();
+    if let Some(reason) = bots::classify_view(user_agent.as_deref(), tracked) {
+        VIEWS.inc(&["flagged"]);
+        return bots::flag_traffic(
+            bots::Kind::View,
+            edition_id,
+            client_id,
+            user_agent,
+            reason,
+            0.0,
+        )
+        .await;
+    }
+
+    let day = UtcDateTime::now().date();
+    if client_id == NO_ID {
+        let visitor = anonymous_visitor(day, ip.as_deref(), user_agent.as_deref());
+        if !first_anonymous_view(visitor, edition_id, day) {
+            VIEWS.inc(&["duplicate"]);
+            return Ok(());
+        }
+    }
+
+    let view = views::ActiveModel {
+        client_id: Set(client_id),
+        edition_id: Set(edition_id),
+        day: Set(day),
+        ..Default::default()
+    };
+
+    let txn = db()
+        .begin()
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to start transaction: {err}")))?;
+
+    // anonymous views are deduplicated in memory only
+    if client_id != NO_ID {
+        let inserted = views::Entity::insert(view)
+            .on_conflict(
+                OnConflict::columns([
+                    views::Column::ClientId,
+                    views::Column::EditionId,
+                    views::Column::Day,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .do_nothing()
+            .exec(&txn)
+            .await
+            .map_err(|err| ServerFnError::new(format!("Failed to record view: {err}")))?;
+
+        // already viewed today
+        if !matches!(inserted, TryInsertResult::Inserted(_)) {
+            VIEWS.inc(&["duplicate"]);
+            return Ok(());
+        }
+    }
+
+    edition::Entity::update_many()
+        .col_expr(
+            edition::Column::Views,
+            Expr::col(edition::Column::Views).add(1),
+        )
+        .filter(edition::Column::Id.eq(edition_id))
+        .exec(&txn)
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to increment views: {err}")))?;
+
+    txn.commit()
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to commit view: {err}")))?;
+
+    VIEWS.inc(&["recorded"]);
+    Ok(())
+}
+
+/// Flushes per client and minute. The client flushes every 5 seconds, this leaves room for a few tabs.
+#[cfg(feature = "server")]
+static CLIENT_READ_LIMIT: LazyLock<RateLimiter> =
+    LazyLock::new(|| RateLimiter::new(60, StdDuration::from_secs(60)));
+/// Flushes per IP and minute. Higher than per client, as a whole school can share an IP.
+#[cfg(feature = "server")]
+static IP_READ_LIMIT: LazyLock<RateLimiter> =
+    LazyLock::new(|| RateLimiter::new(1200, StdDuration::from_secs(60)));
+
+/// When each client (or session, for anonymous clients) last flushed read times of an edition
+#[cfg(feature = "server")]
+static LAST_FLUSH: LazyLock<Mutex<HashMap<(Uuid, EditionId), Instant>>> =
+    LazyLock::new(Default::default);
+
+/// Clients (or sessions) whose last flush is remembered at most
+#[cfg(feature = "server")]
+const MAX_TRACKED_FLUSHES: usize = 100_000;
+/// Flushes further apart than this are treated as the first flush of a client
+#[cfg(feature = "server")]
+const FLUSH_EXPIRY: StdDuration = StdDuration::from_secs(30 * 60);
+/// Read time (ms) allowed for the first flush of a client, for which there is no previous flush
+#[cfg(feature = "server")]
+const FIRST_FLUSH_READ_TIME: f32 = 10_000.0;
+/// Allowance (ms) for timer and network jitter between flushes
+#[cfg(feature = "server")]
+const FLUSH_JITTER: f32 = 1_000.0;
+
+/// The read time (ms) a client may have accumulated since its last flush of an edition.
+/// Anonymous clients are told apart by their session.
+/// Records this flush as its last one.
+#[cfg(feature = "server")]
+fn elapsed_since_last_flush(client_id: Uuid, session_id: Uuid, edition_id: EditionId) -> f32 {
+    let key = if client_id == NO_ID {
+        session_id
+    } else {
+        client_id
+    };
+
+    let now = Instant::now();
+    let mut last_flush = LAST_FLUSH.lock().unwrap_or_else(|err| err.into_inner());
+
+    if last_flush.len() >= 1024 {
+        last_flush.retain(|_, last| now - *last < FLUSH_EXPIRY);
+    }
+    // forgetting flushes only allows a first flush again, so this bounds memory safely
+    if last_flush.len() >= MAX_TRACKED_FLUSHES {
+        last_flush.clear();
+    }
+
+    match last_flush.insert((key, edition_id), now) {
+        Some(last) if now - last < FLUSH_EXPIRY => (now - last).as_millis() as f32 + FLUSH_JITTER,
+        _ => FIRST_FLUSH_READ_TIME,
+    }
+}
+
+/// Check a flush against the rate limits and return the page count of its edition.
+/// Fails for unknown editions.
+#[cfg(feature = "server")]
+pub async fn check_flush(client_id: Uuid, edition_id: EditionId) -> Result<usize, ServerFnError> {
+    use crate::{
+        components::count_pages,
+        db::{db, entities::edition},
+        rate_limit,
+    };
+
+    // anonymous clients are only limited by IP
+    if (client_id != NO_ID && !CLIENT_READ_LIMIT.check(client_id.to_string()))
+        || rate_limit::client_ip().is_some_and(|ip| !IP_READ_LIMIT.check(ip))
+    {
+        return Err(ServerFnError::new("Too many read time flushes"));
+    }
+
+    let edition = edition::Entity::find_by_id(edition_id)
+        .one(db())
+        .await
+        .map_err(|err| ServerFnError::new(err.to_string()))?
+        .ok_or(ServerFnError::new(format!(
+            "Edition {edition_id} not found"
+        )))?;
+
+    Ok(count_pages(&edition).await? as usize)
+}
+
+/// Route of `record_read_times`, which is also posted to directly with `navigator.sendBeacon`
+#[cfg(feature = "web")]
+pub const READ_TIMES_ROUTE: &str = "/api/read-times";
+
+/// The JSON body of a `record_read_times` request
+#[cfg(feature = "web")]
+#[derive(Serialize)]
+pub struct ReadFlush {
+    pub edition_id: EditionId,
+    pub session_id: Uuid,
+    pub page_times: Vec<f32>,
+    pub active: bool,
+}
 
 /// Record read times from a client.
 /// Merge any reads within 30 minutes.
 /// Cap read time at 3 minutes per page. This resets every day to permit re-reads.
-#[server]
+/// Rejects unknown editions, page indices beyond the edition's page count and clients exceeding
+/// the rate limits. The read time of a flush is clamped to the time elapsed since the last one.
+/// `active` is whether the reader interacted with the page during the flush, or just left it open.
+/// Reads reference the session of the flush, which is extended to now.
+#[post("/api/read-times")]
+#[tracing::instrument(skip(session_id, page_times), err)]
 pub async fn record_read_times(
     edition_id: EditionId,
-    page_times: Vec<f32>,
+    session_id: Uuid,
+    mut page_times: Vec<f32>,
+    active: bool,
 ) -> Result<(), ServerFnError> {
-    use crate::db::{db, entities::reads};
+    use crate::{bots, db::db, metrics::READ_FLUSHES};
 
     let db = db();
-    let client_id = get_client_id().map_or(NO_ID, |client_id| client_id.0);
+    let tracked = tracked_client_id();
+    let client_id = tracked.unwrap_or(NO_ID);
+
+    let num_pages = check_flush(client_id, edition_id).await.inspect_err(|_| {
+        READ_FLUSHES.inc(&["rejected"]);
+    })?;
+    if page_times.len() > num_pages {
+        READ_FLUSHES.inc(&["rejected"]);
+        return Err(ServerFnError::new(format!(
+            "Got read times for {} pages, but edition {edition_id} only has {num_pages}",
+            page_times.len()
+        )));
+    }
 
-    let cap_cutoff = UtcDateTime::now() - Duration::days(1);
-    let merge_cutoff = UtcDateTime::now() - Duration::minutes(30);
+    let user_agent = bots::user_agent();
+    if let Some(reason) = bots::classify_read(user_agent.as_deref(), tracked, &page_times) {
+        let read_time = page_times.iter().sum();
+        READ_FLUSHES.inc(&["flagged"]);
+        return bots::flag_traffic(
+            bots::Kind::Read,
+            edition_id,
+            client_id,
+            user_agent,
+            reason,
+            read_time,
+        )
+        .await;
+    }
+
+    // scale all pages down proportionally if more time was reported than has passed
+    let total = page_times.iter().sum::<f32>();
+    let elapsed = elapsed_since_last_flush(client_id, session_id, edition_id);
+    if total > elapsed {
+        let factor = elapsed / total;
+        for time in &mut page_times {
+            *time *= factor;
+        }
+    }
+
+    let now = UtcDateTime::now();
+    store_read_times(
+        db,
+        client_id,
+        session_id,
+        edition_id,
+        &page_times,
+        active,
+        PrimitiveDateTime::new(now.date(), now.time()),
+    )
+    .await
+    .map_err(|err| ServerFnError::new(format!("Failed to record read times: {err}")))?;
+
+    READ_FLUSHES.inc(&["recorded"]);
+    Ok(())
+}
 
-    for (page, time) in page_times
+/// Reads of a client are merged into its read of the page that started at most this long ago
+#[cfg(feature = "server")]
+const MERGE_WINDOW: Duration = Duration::minutes(30);
+/// Maximum read time (ms) of a page per client and day
+#[cfg(feature = "server")]
+const DAILY_PAGE_CAP: f32 = 3. * 60. * 1000.;
+
+/// Store the read times of a flush in a single transaction.
+/// Pages of a client that were read for `DAILY_PAGE_CAP` in the last day are skipped, all others
+/// are added to the client's read of the page that started within `MERGE_WINDOW`, or start a new one.
+/// Active read time is also added to the read's active read time.
+/// Reads of different sessions are kept apart. Anonymous reads are merged and capped per session.
+#[cfg(feature = "server")]
+async fn store_read_times(
+    db: &impl TransactionTrait,
+    client_id: Uuid,
+    session_id: Uuid,
+    edition_id: EditionId,
+    page_times: &[f32],
+    active: bool,
+    now: PrimitiveDateTime,
+) -> Result<(), DbErr> {
+    use crate::db::entities::{read_sources, reads};
+
+    // anonymous readers can only be told apart by their session
+    let reader = if client_id == NO_ID {
+        reads::Column::SessionId.eq(session_id)
+    } else {
+        reads::Column::ClientId.eq(client_id)
+    };
+
+    let txn = db.begin().await?;
+
+    // (page number, merge window) of the reads that can still be merged into. The merge window of
+    // a read is the unix time it started, so concurrent flushes upsert into the same read.
+    let merge_windows: HashMap<i32, i64> = reads::Entity::find()
+        .select_only()
+        .column(reads::Column::PageNumber)
+        .expr(reads::Column::MergeWindow.max())
+        .filter(reads::Column::ClientId.eq(client_id))
+        .filter(reads::Column::EditionId.eq(edition_id))
+        .filter(reads::Column::SessionId.eq(session_id))
+        .filter(reads::Column::Timestamp.gt(now - MERGE_WINDOW))
+        .filter(reads::Column::MergeWindow.is_not_null())
+        .group_by(reads::Column::PageNumber)
+        .into_tuple::<(i32, i64)>()
+        .all(&txn)
+        .await?
+        .into_iter()
+        .collect();
+
+    // (page number, read time) of the pages read in the last day
+    let read_times_within_day: HashMap<i32, f32> = reads::Entity::find()
+        .select_only()
+        .column(reads::Column::PageNumber)
+        .expr(reads::Column::ReadTime.sum())
+        .filter(reader)
+        .filter(reads::Column::EditionId.eq(edition_id))
+        .filter(reads::Column::Timestamp.gt(now - Duration::days(1)))
+        .group_by(reads::Column::PageNumber)
+        .into_tuple::<(i32, f32)>()
+        .all(&txn)
+        .await?
+        .into_iter()
+        .collect();
+
+    let models = page_times
         .iter()
         .enumerate()
-        .filter(|(_, time)| **time != 0.)
-    {
-        // all entities with matching client id edition id and page number
-        let matching_entities = reads::Entity::find()
-            .filter(reads::Column::ClientId.eq(client_id))
-            .filter(reads::Column::EditionId.eq(edition_id))
-            .filter(reads::Column::PageNumber.eq(page as i32));
-
-        // Reads in the last day
-        let read_time_within_day: f32 = matching_entities
-            .clone()
-            .filter(
-                reads::Column::Timestamp
-                    .gt(PrimitiveDateTime::new(cap_cutoff.date(), cap_cutoff.time())),
+        .map(|(page, time)| (page as i32, *time))
+        .filter(|(page, time)| {
+            *time != 0.
+                && read_times_within_day
+                    .get(page)
+                    .is_none_or(|read_time| *read_time < DAILY_PAGE_CAP)
+        })
+        .map(|(page, time)| reads::ActiveModel {
+            client_id: Set(client_id),
+            edition_id: Set(edition_id),
+            page_number: Set(page),
+            read_time: Set(time),
+            active_read_time: Set(Some(if active { time } else { 0.0 })),
+            timestamp: Set(now),
+            merge_window: Set(Some(
+                merge_windows
+                    .get(&page)
+                    .copied()
+                    .unwrap_or_else(|| now.assume_utc().unix_timestamp()),
+            )),
+            session_id: Set(Some(session_id)),
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+
+    if !models.is_empty() {
+        let read_time = models
+            .iter()
+            .map(|model| *model.read_time.as_ref())
+            .sum::<f32>();
+        // the read time of the session's source, so it is kept once the reads are anonymized
+        read_sources::Entity::update_many()
+            .col_expr(
+                read_sources::Column::ReadTime,
+                Expr::col(read_sources::Column::ReadTime).add(read_time),
             )
-            .select_only()
-            .expr(reads::Column::ReadTime.sum())
-            .into_tuple::<Option<f32>>()
-            .one(db)
+            .filter(read_sources::Column::ClientId.eq(client_id))
+            .filter(read_sources::Column::SessionId.eq(session_id))
+            .filter(read_sources::Column::EditionId.eq(edition_id))
+            .exec(&txn)
+            .await?;
+
+        reads::Entity::insert_many(models)
+            .on_conflict(
+                OnConflict::columns([
+                    reads::Column::ClientId,
+                    reads::Column::EditionId,
+                    reads::Column::PageNumber,
+                    reads::Column::MergeWindow,
+                    reads::Column::SessionId,
+                ])
+                .value(
+                    reads::Column::ReadTime,
+                    Expr::col((reads::Entity, reads::Column::ReadTime))
+                        .add(Expr::col((Alias::new("excluded"), reads::Column::ReadTime))),
+                )
+                .value(
+                    reads::Column::ActiveReadTime,
+                    Expr::col((reads::Entity, reads::Column::ActiveReadTime)).add(Expr::col((
+                        Alias::new("excluded"),
+                        reads::Column::ActiveReadTime,
+                    ))),
+                )
+                .to_owned(),
+            )
+            .exec(&txn)
+            .await?;
+    }
+
+    touch_session(&txn, session_id, edition_id, now).await?;
+
+    txn.commit().await
+}
+
+/// Route of `record_progress`, which is also posted to directly with `navigator.sendBeacon`
+#[cfg(feature = "web")]
+pub const PROGRESS_ROUTE: &str = "/api/read-progress";
+
+/// The JSON body of a `record_progress` request
+#[cfg(feature = "web")]
+#[derive(Serialize)]
+pub struct ProgressFlush {
+    pub edition_id: EditionId,
+    pub session_id: Uuid,
+    pub furthest_page: i32,
+}
+
+/// Record the furthest (zero-based) page a client reached in a reading session of an edition.
+/// The session is finished once its furthest page is the last page.
+/// Progress of bots is ignored.
+#[post("/api/read-progress")]
+#[tracing::instrument(skip(session_id), err)]
+pub async fn record_progress(
+    edition_id: EditionId,
+    session_id: Uuid,
+    furthest_page: i32,
+) -> Result<(), ServerFnError> {
+    use crate::{
+        bots,
+        db::{db, entities::read_progress},
+    };
+    use sea_orm::sea_query::Func;
+
+    let tracked = tracked_client_id();
+    let client_id = tracked.unwrap_or(NO_ID);
+
+    let num_pages = check_flush(client_id, edition_id).await?;
+    if usize::try_from(furthest_page).is_ok_and(|page| page >= num_pages) || furthest_page < 0 {
+        return Err(ServerFnError::new(format!(
+            "Page {furthest_page} is out of range, edition {edition_id} has {num_pages} pages"
+        )));
+    }
+
+    let user_agent = bots::user_agent();
+    if bots::classify_view(user_agent.as_deref(), tracked).is_some() {
+        return Ok(());
+    }
+
+    let progress = read_progress::ActiveModel {
+        client_id: Set(client_id),
+        session_id: Set(session_id),
+        edition_id: Set(edition_id),
+        furthest_page: Set(furthest_page),
+        finished: Set(furthest_page as usize + 1 == num_pages),
+        ..Default::default()
+    };
+
+    read_progress::Entity::insert(progress)
+        .on_conflict(
+            OnConflict::columns([
+                read_progress::Column::ClientId,
+                read_progress::Column::SessionId,
+                read_progress::Column::EditionId,
+            ])
+            // progress can arrive out of order
+            .value(
+                read_progress::Column::FurthestPage,
+                Func::greatest([
+                    Expr::col((read_progress::Entity, read_progress::Column::FurthestPage)).into(),
+                    Expr::col((Alias::new("excluded"), read_progress::Column::FurthestPage)).into(),
+                ]),
+            )
+            .value(
+                read_progress::Column::Finished,
+                Func::greatest([
+                    Expr::col((read_progress::Entity, read_progress::Column::Finished)).into(),
+                    Expr::col((Alias::new("excluded"), read_progress::Column::Finished)).into(),
+                ]),
+            )
+            .to_owned(),
+        )
+        .exec(db())
+        .await
+        .map(|_| ())
+        .map_err(|err| ServerFnError::new(format!("Failed to record read progress: {err}")))
+}
+
+#[cfg(all(test, feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::db::{
+        Migrator,
+        entities::{edition, read_sources, reads},
+    };
+    use sea_orm::{Database, DatabaseConnection, QueryOrder};
+    use sea_orm_migration::MigratorTrait;
+    use time::macros::datetime;
+
+    const EDITION: EditionId = 1;
+    const SESSION: Uuid = Uuid::from_u128(1);
+
+    async fn setup() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+        edition::Entity::insert(edition::ActiveModel {
+            id: Set(EDITION),
+            date: Set(time::macros::date!(2026 - 10 - 01)),
+            old_views: Set(0),
+            title: Set(None),
+            hidden: Set(false),
+            views: Set(0),
+            estimated_old_views: Set(None),
+        })
+        .exec(&db)
+        .await
+        .unwrap();
+        db
+    }
+
+    /// (page number, read time) of all reads, ordered by id
+    async fn stored_reads(db: &DatabaseConnection) -> Vec<(i32, f32)> {
+        reads::Entity::find()
+            .order_by_asc(reads::Column::Id)
+            .all(db)
             .await
-            .map_err(|err| ServerFnError::new(err.to_string()))?
-            .flatten()
-            .unwrap_or(0.0);
+            .unwrap()
+            .into_iter()
+            .map(|read| (read.page_number, read.read_time))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn merges_flushes_within_window() {
+        let db = setup().await;
+        let client = Uuid::new_v4();
 
-        let three_minutes_ms = 3. * 60. * 1000.;
-        if read_time_within_day >= three_minutes_ms {
-            continue;
+        store_read_times(
+            &db,
+            client,
+            SESSION,
+            EDITION,
+            &[1000., 0., 500.],
+            true,
+            datetime!(2026-10-19 10:00),
+        )
+        .await
+        .unwrap();
+        store_read_times(
+            &db,
+            client,
+            SESSION,
+            EDITION,
+            &[2000., 300.],
+            true,
+            datetime!(2026-10-19 10:29:59),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(stored_reads(&db).await, [(0, 3000.), (2, 500.), (1, 300.)]);
+    }
+
+    #[tokio::test]
+    async fn merges_across_half_hours() {
+        let db = setup().await;
+        let client = Uuid::new_v4();
+
+        for now in [datetime!(2026-10-19 10:29), datetime!(2026-10-19 10:31)] {
+            store_read_times(&db, client, SESSION, EDITION, &[1000.], true, now)
+                .await
+                .unwrap();
         }
 
-        // try to find an existing entry within the merge cutoff
-        let merge_entity = matching_entities
-            .filter(reads::Column::Timestamp.gt(PrimitiveDateTime::new(
-                merge_cutoff.date(),
-                merge_cutoff.time(),
-            )))
-            .order_by_desc(reads::Column::Timestamp)
-            .one(db)
+        assert_eq!(stored_reads(&db).await, [(0, 2000.)]);
+    }
+
+    #[tokio::test]
+    async fn starts_new_read_after_window() {
+        let db = setup().await;
+        let client = Uuid::new_v4();
+
+        for now in [
+            datetime!(2026-10-19 10:00),
+            datetime!(2026-10-19 10:20),
+            datetime!(2026-10-19 10:30:01),
+            datetime!(2026-10-19 10:40),
+        ] {
+            store_read_times(&db, client, SESSION, EDITION, &[1000.], true, now)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(stored_reads(&db).await, [(0, 2000.), (0, 2000.)]);
+    }
+
+    #[tokio::test]
+    async fn keeps_clients_apart() {
+        let db = setup().await;
+
+        store_read_times(
+            &db,
+            Uuid::new_v4(),
+            SESSION,
+            EDITION,
+            &[1000.],
+            true,
+            datetime!(2026-10-19 10:00),
+        )
+        .await
+        .unwrap();
+        store_read_times(
+            &db,
+            Uuid::new_v4(),
+            SESSION,
+            EDITION,
+            &[1000.],
+            true,
+            datetime!(2026-10-19 10:00),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(stored_reads(&db).await, [(0, 1000.), (0, 1000.)]);
+    }
+
+    #[tokio::test]
+    async fn caps_page_per_day() {
+        let db = setup().await;
+        let client = Uuid::new_v4();
+
+        // the cap is only checked before a flush, so this one may exceed it
+        store_read_times(
+            &db,
+            client,
+            SESSION,
+            EDITION,
+            &[DAILY_PAGE_CAP + 1000.],
+            true,
+            datetime!(2026-10-19 10:00),
+        )
+        .await
+        .unwrap();
+        // capped page is skipped, other pages are still recorded
+        store_read_times(
+            &db,
+            client,
+            SESSION,
+            EDITION,
+            &[1000., 1000.],
+            true,
+            datetime!(2026-10-19 12:00),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            stored_reads(&db).await,
+            [(0, DAILY_PAGE_CAP + 1000.), (1, 1000.)]
+        );
+    }
+
+    #[tokio::test]
+    async fn resets_cap_after_a_day() {
+        let db = setup().await;
+        let client = Uuid::new_v4();
+
+        store_read_times(
+            &db,
+            client,
+            SESSION,
+            EDITION,
+            &[DAILY_PAGE_CAP],
+            true,
+            datetime!(2026-10-19 10:00),
+        )
+        .await
+        .unwrap();
+        store_read_times(
+            &db,
+            client,
+            SESSION,
+            EDITION,
+            &[1000.],
+            true,
+            datetime!(2026-10-20 10:00:01),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(stored_reads(&db).await, [(0, DAILY_PAGE_CAP), (0, 1000.)]);
+    }
+
+    #[tokio::test]
+    async fn separates_active_time() {
+        let db = setup().await;
+        let client = Uuid::new_v4();
+
+        store_read_times(
+            &db,
+            client,
+            SESSION,
+            EDITION,
+            &[1000.],
+            true,
+            datetime!(2026-10-19 10:00),
+        )
+        .await
+        .unwrap();
+        store_read_times(
+            &db,
+            client,
+            SESSION,
+            EDITION,
+            &[500.],
+            false,
+            datetime!(2026-10-19 10:01),
+        )
+        .await
+        .unwrap();
+
+        let read = reads::Entity::find().one(&db).await.unwrap().unwrap();
+        assert_eq!(read.read_time, 1500.);
+        assert_eq!(read.active_read_time, Some(1000.));
+    }
+
+    #[tokio::test]
+    async fn adds_read_time_to_source() {
+        let db = setup().await;
+        let client = Uuid::new_v4();
+
+        read_sources::Entity::insert(read_sources::ActiveModel {
+            client_id: Set(client),
+            session_id: Set(SESSION),
+            edition_id: Set(EDITION),
+            entry: Set("direct".to_string()),
+            ..Default::default()
+        })
+        .exec(&db)
+        .await
+        .unwrap();
+
+        for (session, now) in [
+            (SESSION, datetime!(2026-10-19 10:00)),
+            (SESSION, datetime!(2026-10-19 11:00)),
+            (Uuid::from_u128(2), datetime!(2026-10-19 11:00)),
+        ] {
+            store_read_times(&db, client, session, EDITION, &[1000., 500.], true, now)
+                .await
+                .unwrap();
+        }
+
+        let source = read_sources::Entity::find()
+            .one(&db)
             .await
-            .map_err(|err| ServerFnError::new(err.to_string()))?;
-
-        match merge_entity {
-            Some(entity) => {
-                let mut active: reads::ActiveModel = entity.into();
-                let current_read_time = active.read_time.take().unwrap_or(0.0);
-                active.read_time = Set(current_read_time + *time);
-
-                active
-                    .update(db)
-                    .await
-                    .map_err(|err| ServerFnError::new(err.to_string()))?;
-            }
-            None => {
-                let entity = reads::ActiveModel {
-                    client_id: Set(client_id),
-                    edition_id: Set(edition_id),
-                    page_number: Set(page as i32),
-                    read_time: Set(*time),
-                    ..Default::default()
-                };
-                reads::Entity::insert(entity)
-                    .exec(db)
-                    .await
-                    .map_err(|err| ServerFnError::new(err.to_string()))?;
-            }
+            .unwrap()
+            .unwrap();
+        assert_eq!(source.read_time, 3000.);
+    }
+
+    #[tokio::test]
+    async fn merges_anonymous_reads_per_session() {
+        let db = setup().await;
+        let other_session = Uuid::from_u128(2);
+
+        for (session, now) in [
+            (SESSION, datetime!(2026-10-19 10:00)),
+            (SESSION, datetime!(2026-10-19 10:10)),
+            (other_session, datetime!(2026-10-19 10:10)),
+        ] {
+            store_read_times(&db, NO_ID, session, EDITION, &[1000.], true, now)
+                .await
+                .unwrap();
         }
+
+        assert_eq!(stored_reads(&db).await, [(0, 2000.), (0, 1000.)]);
     }
 
-    Ok(())
+    #[tokio::test]
+    async fn caps_anonymous_reads_per_session() {
+        let db = setup().await;
+        let other_session = Uuid::from_u128(2);
+
+        for (session, now) in [
+            (SESSION, datetime!(2026-10-19 10:00)),
+            (SESSION, datetime!(2026-10-19 12:00)),
+            (other_session, datetime!(2026-10-19 12:00)),
+        ] {
+            store_read_times(&db, NO_ID, session, EDITION, &[DAILY_PAGE_CAP], true, now)
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            stored_reads(&db).await,
+            [(0, DAILY_PAGE_CAP), (0, DAILY_PAGE_CAP)]
+        );
+    }
+
+    #[test]
+    fn deduplicates_anonymous_views_per_visitor_and_day() {
+        let day = time::macros::date!(2026 - 10 - 19);
+        let visitor = |day, ip| anonymous_visitor(day, Some(ip), Some("Firefox"));
+
+        let first = visitor(day, "192.0.2.1");
+        assert!(first_anonymous_view(first, EDITION, day));
+        // a reload or new tab is the same visitor
+        assert!(!first_anonymous_view(
+            visitor(day, "192.0.2.1"),
+            EDITION,
+            day
+        ));
+        assert!(first_anonymous_view(
+            visitor(day, "192.0.2.2"),
+            EDITION,
+            day
+        ));
+        assert!(first_anonymous_view(
+            anonymous_visitor(day, Some("192.0.2.1"), Some("Chrome")),
+            EDITION,
+            day
+        ));
+
+        let next_day = day.next_day().unwrap();
+        let next = visitor(next_day, "192.0.2.1");
+        assert_ne!(first, next);
+        assert!(first_anonymous_view(next, EDITION, next_day));
+    }
+
+    #[tokio::test]
+    async fn keeps_sessions_apart() {
+        let db = setup().await;
+        let client = Uuid::new_v4();
+
+        for session in [SESSION, Uuid::from_u128(2)] {
+            store_read_times(
+                &db,
+                client,
+                session,
+                EDITION,
+                &[1000.],
+                true,
+                datetime!(2026-10-19 10:00),
+            )
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(stored_reads(&db).await, [(0, 1000.), (0, 1000.)]);
+    }
+
+    #[tokio::test]
+    async fn extends_session() {
+        use crate::db::entities::{session_editions, sessions};
+
+        let db = setup().await;
+        let client = Uuid::new_v4();
+        sessions::Entity::insert(sessions::ActiveModel {
+            session_id: Set(SESSION),
+            client_id: Set(client),
+            start: Set(datetime!(2026-10-19 10:00)),
+            end: Set(datetime!(2026-10-19 10:00)),
+            device_class: Set("desktop".to_string()),
+            entry: Set("/".to_string()),
+            ..Default::default()
+        })
+        .exec(&db)
+        .await
+        .unwrap();
+
+        // page 0 is read again after the merge window, but only counts once
+        for (page_times, now) in [
+            (&[1000.][..], datetime!(2026-10-19 10:05)),
+            (&[0., 1000.], datetime!(2026-10-19 10:10)),
+            (&[1000.], datetime!(2026-10-19 10:45)),
+        ] {
+            store_read_times(&db, client, SESSION, EDITION, page_times, true, now)
+                .await
+                .unwrap();
+        }
+
+        let session = sessions::Entity::find().one(&db).await.unwrap().unwrap();
+        assert_eq!(session.end, datetime!(2026-10-19 10:45));
+        let editions = session_editions::Entity::find().all(&db).await.unwrap();
+        assert_eq!(editions.len(), 1);
+        assert_eq!(editions[0].edition_id, EDITION);
+        assert_eq!(editions[0].pages, 2);
+    }
 }
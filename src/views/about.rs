@@ -43,6 +43,7 @@ pub static TEAM: RwLock<Team> = RwLock::const_new(Team {
 });
 
 #[server]
+#[tracing::instrument(err)]
 async fn get_team() -> Result<Team> {
     use rand::seq::{IndexedRandom, SliceRandom};
 
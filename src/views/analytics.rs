@@ -0,0 +1,437 @@
+use crate::{
+    Route,
+    analytics::{
+        PageHeat, fetch_analytics, fetch_edition_analytics, fetch_heatmap,
+        fetch_read_time_distribution,
+    },
+    cohorts::fetch_cohorts,
+    components::{AdminOnly, EditionId},
+    db::entities::edition,
+    i18n,
+    retention::{RetentionMode, fetch_retention_report},
+    sync_status::fetch_sync_status,
+};
+use dioxus::prelude::*;
+
+const ANALYTICS_CSS: Asset = asset!("/assets/styling/analytics.css");
+
+/// Format milliseconds as minutes with one decimal
+fn minutes(milliseconds: f32) -> String {
+    format!("{:.1}", milliseconds / 60_000.0)
+}
+
+/// Format the estimated old views of an edition. Empty without old views, and marked as not
+/// estimable if there are old views but no estimate.
+fn estimate(edition: &edition::Model, lang: i18n::Language) -> String {
+    match edition.estimated_old_views {
+        Some(estimate) => format!("≈{estimate}"),
+        None if edition.old_views > 0 => lang.not_estimable().to_string(),
+        None => String::new(),
+    }
+}
+
+#[component]
+pub fn Analytics() -> Element {
+    rsx! {
+        document::Link { rel: "stylesheet", href: ANALYTICS_CSS }
+        AdminOnly {
+            AnalyticsOverview {}
+            CohortReport {}
+            RetentionReport {}
+        }
+    }
+}
+
+#[component]
+fn AnalyticsOverview() -> Element {
+    let analytics = use_server_future(move || async move { fetch_analytics().await })?;
+
+    let lang = i18n::use_lang();
+
+    rsx! {
+        div { class: "analytics",
+            Link { to: Route::ExportStatus {}, "{lang.read().export_status_title()}" }
+            h1 { class: "text-4xl", "{lang.read().analytics_title()}" }
+
+            match &*analytics.read_unchecked() {
+                Some(Ok(analytics)) => rsx! {
+                    div { class: "chart", dangerous_inner_html: "{analytics.read_time_chart}" }
+                    div { class: "chart", dangerous_inner_html: "{analytics.readers_chart}" }
+                    table {
+                        tr {
+                            th { "{lang.read().edition()}" }
+                            th { "{lang.read().views()}" }
+                            th { "{lang.read().old_views()}" }
+                            th { "{lang.read().estimated_old_views()}" }
+                            th { "{lang.read().read_time_minutes()}" }
+                            th { "{lang.read().unique_readers()}" }
+                        }
+                        for stats in analytics.editions.iter().rev() {
+                            tr {
+                                td {
+                                    Link {
+                                        to: Route::EditionAnalytics {
+                                            id: stats.edition.id,
+                                        },
+                                        "{stats.edition.label()}"
+                                    }
+                                }
+                                td { "{stats.edition.views}" }
+                                td { "{stats.edition.old_views}" }
+                                td { "{estimate(&stats.edition, lang())}" }
+                                td { "{minutes(stats.read_time)}" }
+                                td { "{stats.unique_readers}" }
+                            }
+                        }
+                    }
+                },
+                Some(Err(e)) => rsx! { "{lang.read().error_loading_analytics()}: {e}" },
+                None => rsx! { "{lang.read().loading_analytics()}" },
+            }
+        }
+    }
+}
+
+/// Format a ratio as a percentage, which is empty if there is nothing to relate to
+fn percent(ratio: Option<f64>) -> String {
+    ratio.map_or(String::new(), |ratio| format!("{:.0}%", ratio * 100.0))
+}
+
+#[component]
+fn CohortReport() -> Element {
+    let report = use_server_future(move || async move { fetch_cohorts().await })?;
+
+    let lang = i18n::use_lang();
+
+    rsx! {
+        div { class: "analytics",
+            h2 { class: "text-3xl", "{lang.read().cohorts()}" }
+
+            match &*report.read_unchecked() {
+                Some(Ok(report)) => rsx! {
+                    p { "{lang.read().multi_edition_readers()}: {report.multi_edition_readers} / {report.readers}" }
+                    table {
+                        tr {
+                            th { "{lang.read().edition()}" }
+                            th { "{lang.read().unique_readers()}" }
+                            th { "{lang.read().new_readers()}" }
+                            th { "{lang.read().returning_readers()}" }
+                            th { "{lang.read().retained_from_previous()}" }
+                            th { "{lang.read().cohort_retained()}" }
+                        }
+                        for cohort in report.editions.iter().rev() {
+                            tr {
+                                td { "{cohort.edition.label()}" }
+                                td { "{cohort.readers}" }
+                                td { "{cohort.new_readers}" }
+                                td { "{cohort.returning_readers}" }
+                                td {
+                                    "{cohort.retained_from_previous.map_or(String::new(), |retained| retained.to_string())} "
+                                    "{percent(cohort.retention_rate)}"
+                                }
+                                td { "{cohort.cohort_retained}" }
+                            }
+                        }
+                    }
+                    table {
+                        tr {
+                            th { "{lang.read().editions_read()}" }
+                            th { "{lang.read().unique_readers()}" }
+                        }
+                        for (editions, readers) in &report.editions_per_reader {
+                            tr {
+                                td { "{editions}" }
+                                td { "{readers}" }
+                            }
+                        }
+                    }
+                },
+                Some(Err(e)) => rsx! { "{lang.read().error_loading_analytics()}: {e}" },
+                None => rsx! { "{lang.read().loading_analytics()}" },
+            }
+        }
+    }
+}
+
+#[component]
+fn RetentionReport() -> Element {
+    let report = use_server_future(move || async move { fetch_retention_report().await })?;
+
+    let lang = i18n::use_lang();
+
+    rsx! {
+        div { class: "analytics",
+            h2 { class: "text-3xl", "{lang.read().data_retention()}" }
+
+            match &*report.read_unchecked() {
+                Some(Ok(Some(report))) => rsx! {
+                    p { "{lang.read().retention_cutoff()} {report.cutoff}" }
+                    p {
+                        "{lang.read().affected_reads()}: {report.affected_reads} "
+                        match report.policy.mode {
+                            RetentionMode::Delete => lang.read().retention_delete(),
+                            RetentionMode::Anonymize => lang.read().retention_anonymize(),
+                        }
+                    }
+                    p { "{lang.read().affected_readers()}: {report.affected_clients}" }
+                    if report.policy.dry_run {
+                        p { "{lang.read().retention_dry_run()}" }
+                    }
+                },
+                Some(Ok(None)) => rsx! { "{lang.read().no_retention_policy()}" },
+                Some(Err(e)) => rsx! { "{lang.read().error_loading_analytics()}: {e}" },
+                None => rsx! { "{lang.read().loading_analytics()}" },
+            }
+        }
+    }
+}
+
+#[component]
+pub fn ExportStatus() -> Element {
+    rsx! {
+        document::Link { rel: "stylesheet", href: ANALYTICS_CSS }
+        AdminOnly { SyncStatusReport {} }
+    }
+}
+
+#[component]
+fn SyncStatusReport() -> Element {
+    let status = use_server_future(move || async move { fetch_sync_status().await })?;
+
+    let lang = i18n::use_lang();
+
+    rsx! {
+        div { class: "analytics",
+            Link { to: Route::Analytics {}, "{lang.read().analytics_title()}" }
+            h1 { class: "text-4xl", "{lang.read().export_status_title()}" }
+
+            match &*status.read_unchecked() {
+                Some(Ok(subjects)) => rsx! {
+                    table {
+                        tr {
+                            th { "{lang.read().export_subject()}" }
+                            th { "{lang.read().export_state()}" }
+                            th { "{lang.read().last_success()}" }
+                            th { "{lang.read().last_failure()}" }
+                            th { "{lang.read().consecutive_failures()}" }
+                            th { "{lang.read().last_error()}" }
+                        }
+                        for status in subjects {
+                            tr { class: if status.failing() { "failing" },
+                                td { "{status.subject}" }
+                                td {
+                                    if status.failing() {
+                                        "{lang.read().export_failing()}"
+                                    } else if status.last_success.is_some() {
+                                        "{lang.read().export_ok()}"
+                                    } else {
+                                        "{lang.read().export_pending()}"
+                                    }
+                                }
+                                td { "{timestamp(status.last_success)}" }
+                                td { "{timestamp(status.last_failure)}" }
+                                td { "{status.consecutive_failures}" }
+                                td { "{status.last_error.as_deref().unwrap_or_default()}" }
+                            }
+                        }
+                    }
+                },
+                Some(Err(e)) => rsx! { "{lang.read().error_loading_analytics()}: {e}" },
+                None => rsx! { "{lang.read().loading_analytics()}" },
+            }
+        }
+    }
+}
+
+/// Format a UTC timestamp, which is empty if there is none
+fn timestamp(timestamp: Option<time::PrimitiveDateTime>) -> String {
+    timestamp.map_or(String::new(), |timestamp| format!("{timestamp} UTC"))
+}
+
+#[component]
+pub fn EditionAnalytics(id: EditionId) -> Element {
+    rsx! {
+        document::Link { rel: "stylesheet", href: ANALYTICS_CSS }
+        AdminOnly { EditionReport { id } }
+    }
+}
+
+#[component]
+fn EditionReport(id: EditionId) -> Element {
+    let report = use_server_future(move || async move { fetch_edition_analytics(id).await })?;
+
+    let lang = i18n::use_lang();
+
+    rsx! {
+        div { class: "analytics",
+            Link { to: Route::Analytics {}, "{lang.read().analytics_title()}" }
+            " | "
+            Link { to: Route::EditionHeatmap { id }, "{lang.read().heatmap_title()}" }
+
+            match &*report.read_unchecked() {
+                Some(Ok(report)) => rsx! {
+                    h1 { class: "text-4xl", "{report.stats.edition.label()}" }
+                    table {
+                        tr {
+                            th { "{lang.read().views()}" }
+                            th { "{lang.read().old_views()}" }
+                            th { "{lang.read().estimated_old_views()}" }
+                            th { "{lang.read().read_time_minutes()}" }
+                            th { "{lang.read().unique_readers()}" }
+                        }
+                        tr {
+                            td { "{report.stats.edition.views}" }
+                            td { "{report.stats.edition.old_views}" }
+                            td { "{estimate(&report.stats.edition, lang())}" }
+                            td { "{minutes(report.stats.read_time)}" }
+                            td { "{report.stats.unique_readers}" }
+                        }
+                    }
+                    div { class: "chart", dangerous_inner_html: "{report.page_chart}" }
+                    div { class: "chart", dangerous_inner_html: "{report.readers_over_time_chart}" }
+                    table {
+                        tr {
+                            th { "{lang.read().page()}" }
+                            th { "{lang.read().read_time_minutes()}" }
+                            th { "{lang.read().unique_readers()}" }
+                        }
+                        for page in &report.pages {
+                            tr {
+                                td { "{page.page + 1}" }
+                                td { "{minutes(page.read_time)}" }
+                                td { "{page.unique_readers}" }
+                            }
+                        }
+                    }
+                    h2 { class: "text-3xl", "{lang.read().sessions()}" }
+                    table {
+                        tr {
+                            th { "{lang.read().sessions()}" }
+                            th { "{lang.read().average_session_length_minutes()}" }
+                            th { "{lang.read().pages_per_session()}" }
+                        }
+                        tr {
+                            td { "{report.sessions.sessions}" }
+                            td { "{minutes(report.sessions.average_length as f32)}" }
+                            td { "{report.sessions.pages_per_session:.1}" }
+                        }
+                    }
+                    h2 { class: "text-3xl", "{lang.read().sources()}" }
+                    table {
+                        tr {
+                            th { "{lang.read().entry_point()}" }
+                            th { "{lang.read().referrer()}" }
+                            th { "utm_source" }
+                            th { "utm_medium" }
+                            th { "utm_campaign" }
+                            th { "{lang.read().read_time_minutes()}" }
+                            th { "{lang.read().unique_readers()}" }
+                        }
+                        for source in &report.sources {
+                            tr {
+                                td { "{source.entry}" }
+                                td { "{source.referrer.as_deref().unwrap_or_default()}" }
+                                td { "{source.campaign.source.as_deref().unwrap_or_default()}" }
+                                td { "{source.campaign.medium.as_deref().unwrap_or_default()}" }
+                                td { "{source.campaign.campaign.as_deref().unwrap_or_default()}" }
+                                td { "{minutes(source.read_time)}" }
+                                td { "{source.readers}" }
+                            }
+                        }
+                    }
+                },
+                Some(Err(e)) => rsx! { "{lang.read().error_loading_analytics()}: {e}" },
+                None => rsx! { "{lang.read().loading_analytics()}" },
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum HeatMetric {
+    Median,
+    Total,
+}
+
+#[component]
+pub fn EditionHeatmap(id: EditionId) -> Element {
+    rsx! {
+        document::Link { rel: "stylesheet", href: ANALYTICS_CSS }
+        AdminOnly { Heatmap { id } }
+    }
+}
+
+#[component]
+fn Heatmap(id: EditionId) -> Element {
+    let heatmap = use_server_future(move || async move { fetch_heatmap(id).await })?;
+    let mut metric = use_signal(|| HeatMetric::Median);
+    let mut selected_page = use_signal(|| None);
+    let distribution = use_resource(move || async move {
+        match selected_page() {
+            Some(page) => Some(fetch_read_time_distribution(id, page).await),
+            None => None,
+        }
+    });
+
+    let lang = i18n::use_lang();
+
+    rsx! {
+        div { class: "analytics",
+            Link { to: Route::EditionAnalytics { id }, "{lang.read().analytics_title()}" }
+            h1 { class: "text-4xl", "{lang.read().heatmap_title()}" }
+
+            select {
+                onchange: move |event| {
+                    metric
+                        .set(
+                            if event.value() == "total" {
+                                HeatMetric::Total
+                            } else {
+                                HeatMetric::Median
+                            },
+                        );
+                },
+                option { value: "median", "{lang.read().median_read_time()}" }
+                option { value: "total", "{lang.read().total_read_time()}" }
+            }
+
+            match &*heatmap.read_unchecked() {
+                Some(Ok(heatmap)) => {
+                    let value = |page: &PageHeat| match metric() {
+                        HeatMetric::Median => page.median,
+                        HeatMetric::Total => page.total,
+                    };
+                    let max = heatmap.pages.iter().map(value).fold(0.0, f32::max).max(1.0);
+                    rsx! {
+                        div { id: "heatmap",
+                            for (i , page) in heatmap.pages.iter().enumerate() {
+                                div {
+                                    class: "heatmap-page",
+                                    onclick: move |_| selected_page.set(Some(i)),
+                                    img { src: "/svgs/{heatmap.edition.date}/{i + 1}.svg" }
+                                    div {
+                                        class: "heatmap-tint",
+                                        style: "opacity: {value(page) / max:.2}",
+                                    }
+                                    span { class: "heatmap-label",
+                                        "{i + 1}: {page.median / 1000.0:.1}s / {page.total / 60_000.0:.1}min ({page.reads})"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                Some(Err(e)) => rsx! { "{lang.read().error_loading_analytics()}: {e}" },
+                None => rsx! { "{lang.read().loading_analytics()}" },
+            }
+
+            match &*distribution.read_unchecked() {
+                Some(Some(Ok(svg))) => rsx! {
+                    div { class: "chart", dangerous_inner_html: "{svg}" }
+                },
+                Some(Some(Err(e))) => rsx! { "{lang.read().error_loading_analytics()}: {e}" },
+                _ => rsx! {},
+            }
+        }
+    }
+}
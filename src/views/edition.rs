@@ -1,19 +1,31 @@
 use crate::{
-    components::{EditionId, ViewEdition, view_edition},
+    attribution::use_record_source,
+    components::{EditionData, EditionId, fetch_edition},
     i18n,
-    track_views::ensure_client_id_set,
+    sessions::SESSION_ID,
+    track_views::{ensure_client_id_set, record_view},
     views::Feedback,
 };
 use dioxus::prelude::*;
+use uuid::Uuid;
 
 #[component]
 pub fn Edition(id: EditionId) -> Element {
-    let data = use_server_future(move || async move { view_edition(id).await })?;
+    let data = use_server_future(move || async move { fetch_edition(id).await })?;
     use_hook(ensure_client_id_set);
+    let session_id = SESSION_ID();
+    use_record_source(id, session_id);
+
+    // only runs on the client, so server side rendering doesn't count as a view
+    use_effect(use_reactive!(|id| {
+        spawn(async move {
+            let _ = record_view(id).await;
+        });
+    }));
 
     let lang = i18n::use_lang();
 
-    track_reads(data, id);
+    track_reads(data, id, session_id);
 
     rsx! {
         div {
@@ -40,12 +52,37 @@ pub fn Edition(id: EditionId) -> Element {
     }
 }
 
+/// Without scroll, touch, pointer or key events for this long, the reader is passive
+#[cfg(feature = "web")]
+const IDLE_AFTER_MS: f64 = 30_000.0;
+/// Without any of these events for this long, the edition was probably left open and isn't counted
 #[cfg(feature = "web")]
-fn track_reads(data: Resource<Result<ViewEdition, ServerFnError>>, edition_id: EditionId) {
+const STOP_COUNTING_AFTER_MS: f64 = 5. * 60_000.0;
+/// A page counts as reached once it was read for this long
+#[cfg(feature = "web")]
+const REACHED_AFTER_MS: f64 = 2_000.0;
+#[cfg(feature = "web")]
+const ACTIVITY_EVENTS: [&str; 6] = [
+    "scroll",
+    "wheel",
+    "touchstart",
+    "pointermove",
+    "pointerdown",
+    "keydown",
+];
+
+#[cfg(feature = "web")]
+fn track_reads(
+    data: Resource<Result<EditionData, ServerFnError>>,
+    edition_id: EditionId,
+    session_id: Uuid,
+) {
+    use crate::track_views::{PROGRESS_ROUTE, ProgressFlush, READ_TIMES_ROUTE, ReadFlush};
     use dioxus::core::Runtime;
     use std::rc::Rc;
     use web_sys::{
-        Element, IntersectionObserver, IntersectionObserverEntry, IntersectionObserverInit,
+        Element, EventTarget, IntersectionObserver, IntersectionObserverEntry,
+        IntersectionObserverInit,
         js_sys::{
             self,
             wasm_bindgen::{JsValue, prelude::Closure},
@@ -58,7 +95,11 @@ fn track_reads(data: Resource<Result<ViewEdition, ServerFnError>>, edition_id: E
     let scope = runtime.current_scope_id();
 
     let mut visibilities = use_signal(|| Vec::new());
+    // read time of the current flush, which is either active or passive
     let mut acc = use_signal(|| Vec::new());
+    // read time since the edition was opened, to find the furthest page reached
+    let mut session_times = use_signal(|| Vec::new());
+    let mut reported_page = use_signal(|| None);
 
     let mut observer = use_signal(|| None);
     let mut _observer_callback = use_signal(|| None);
@@ -77,6 +118,7 @@ fn track_reads(data: Resource<Result<ViewEdition, ServerFnError>>, edition_id: E
             let num_pages = data.num_pages as usize;
             visibilities.set(vec![0.0; num_pages]);
             acc.set(vec![0.0; num_pages]);
+            session_times.set(vec![0.0; num_pages]);
 
             let callback = Closure::new({
                 let runtime = runtime.clone();
@@ -137,78 +179,260 @@ fn track_reads(data: Resource<Result<ViewEdition, ServerFnError>>, edition_id: E
 
     let mut last_sample = use_signal(now_ms);
     let mut last_flush = use_signal(now_ms);
+    let mut last_activity = use_signal(now_ms);
+    let mut active = use_signal(|| true);
     let mut interval_handle = use_signal(|| None);
     let mut _tick = use_signal(|| None);
+    let mut _listeners = use_signal(Vec::new);
 
-    // initialize ticks
-    use_effect(move || {
-        if !initialized() || interval_handle().is_some() {
+    // Split time between the visible pages.
+    // Pages are weighted by their visible fraction and their distance to the viewport centre.
+    let mut accumulate = move |delta_t: f64| {
+        let Some(window) = window() else {
+            return;
+        };
+        let document = window.document();
+        let half_width = window
+            .inner_width()
+            .ok()
+            .and_then(|width| width.as_f64())
+            .unwrap_or_default()
+            / 2.0;
+        let half_height = window
+            .inner_height()
+            .ok()
+            .and_then(|height| height.as_f64())
+            .unwrap_or_default()
+            / 2.0;
+
+        let weights = visibilities()
+            .iter()
+            .enumerate()
+            .map(|(page, visibility)| {
+                if *visibility <= 0.0 || half_width <= 0.0 || half_height <= 0.0 {
+                    return *visibility;
+                }
+                let Some(element) = document.as_ref().and_then(|document| {
+                    document.get_element_by_id(&format!("edition-page-{}", page + 1))
+                }) else {
+                    return *visibility;
+                };
+                let rect = element.get_bounding_client_rect();
+                let dx = (rect.left() + rect.right()) / 2.0 - half_width;
+                let dy = (rect.top() + rect.bottom()) / 2.0 - half_height;
+                // 1 in the centre, 1/2 at the edge of the viewport
+                let distance = ((dx / half_width).powi(2) + (dy / half_height).powi(2)).sqrt();
+                visibility / (1.0 + distance)
+            })
+            .collect::<Vec<_>>();
+
+        let total = weights.iter().sum::<f64>();
+        if total <= 0.0 || delta_t <= 0.0 {
             return;
         }
 
-        let tick = Closure::new({
-            let runtime = runtime.clone();
-            move || {
-                runtime.in_scope(scope, || {
-                    let now = now_ms();
-                    let delta_t = now - last_sample();
-                    last_sample.set(now);
+        for ((weight, mut acc_elem), mut session_elem) in weights
+            .iter()
+            .zip(acc.iter_mut())
+            .zip(session_times.iter_mut())
+        {
+            *acc_elem += delta_t * (*weight / total);
+            *session_elem += delta_t * (*weight / total);
+        }
+    };
 
-                    if window()
-                        .and_then(|window| window.document())
-                        .map(|document| document.hidden() || !document.has_focus().unwrap_or(true))
-                        .unwrap_or_default()
-                    {
-                        return;
-                    }
+    // The furthest page read for at least `REACHED_AFTER_MS`, if it wasn't reported yet
+    let mut take_progress = move || {
+        let furthest = session_times()
+            .iter()
+            .rposition(|time| *time >= REACHED_AFTER_MS)?;
+        if reported_page().is_some_and(|reported| reported >= furthest) {
+            return None;
+        }
+        reported_page.set(Some(furthest));
+        Some(furthest as i32)
+    };
 
-                    let visibilities = visibilities();
+    // Take a snapshot and reset accumulator. None if nothing was read since the last flush.
+    let mut take_snapshot = move || {
+        let snapshot = acc()
+            .into_iter()
+            .map(|float| float as f32)
+            .collect::<Vec<_>>();
+        for mut value in acc.iter_mut() {
+            *value = 0.0;
+        }
 
-                    let total = visibilities.iter().sum::<f64>();
-                    if total <= 0.0 || delta_t <= 0.0 {
-                        return;
-                    }
+        (!snapshot.iter().all(|value| *value == 0.0)).then_some(snapshot)
+    };
 
-                    for (visibility, mut acc_elem) in visibilities.iter().zip(acc.iter_mut()) {
-                        *acc_elem += delta_t * (*visibility / total)
-                    }
+    let mut flush = move |now: f64| {
+        use crate::track_views::{record_progress, record_read_times};
 
-                    if now - last_flush() >= 5000.0 {
-                        use crate::track_views::record_read_times;
+        last_flush.set(now);
 
-                        last_flush.set(now);
+        if let Some(snapshot) = take_snapshot() {
+            let active = active();
+            spawn(async move {
+                record_read_times(edition_id, session_id, snapshot, active).await;
+            });
+        }
+        if let Some(furthest_page) = take_progress() {
+            spawn(async move {
+                let _ = record_progress(edition_id, session_id, furthest_page).await;
+            });
+        }
+    };
 
-                        // Take a snapshot and reset accumulator
-                        let snapshot = acc()
-                            .into_iter()
-                            .map(|float| float as f32)
-                            .collect::<Vec<_>>();
-                        for mut value in acc.iter_mut() {
-                            *value = 0.0;
+    // initialize ticks
+    use_effect({
+        let runtime = runtime.clone();
+        move || {
+            if !initialized() || interval_handle().is_some() {
+                return;
+            }
+
+            let tick = Closure::new({
+                let runtime = runtime.clone();
+                move || {
+                    runtime.in_scope(scope, || {
+                        let now = now_ms();
+                        let delta_t = now - last_sample();
+                        last_sample.set(now);
+
+                        if window()
+                            .and_then(|window| window.document())
+                            .map(|document| {
+                                document.hidden() || !document.has_focus().unwrap_or(true)
+                            })
+                            .unwrap_or_default()
+                        {
+                            return;
                         }
 
-                        if snapshot.iter().all(|value| *value == 0.0) {
+                        let idle = now - last_activity();
+                        // probably left open, not read
+                        if idle >= STOP_COUNTING_AFTER_MS {
                             return;
                         }
 
-                        spawn(async move {
-                            record_read_times(edition_id, snapshot).await;
-                        });
+                        // flushes only contain active or passive time
+                        let is_active = idle < IDLE_AFTER_MS;
+                        if is_active != active() {
+                            flush(now);
+                            active.set(is_active);
+                        }
+
+                        accumulate(delta_t);
+
+                        if now - last_flush() >= 5000.0 {
+                            flush(now);
+                        }
+                    });
+                }
+            });
+
+            let handle = window()
+                .unwrap()
+                .set_interval_with_callback_and_timeout_and_arguments_0(
+                    tick.as_ref().unchecked_ref(),
+                    250,
+                )
+                .expect("setInterval should work");
+
+            interval_handle.set(Some(handle));
+            _tick.set(Some(Rc::new(tick))); // keep callback alive
+        }
+    });
+
+    // register event listeners
+    use_effect(move || {
+        if !initialized() || !_listeners.peek().is_empty() {
+            return;
+        }
+
+        // flush the rest when the tab is hidden or closed, which the interval doesn't survive
+        let on_hide = Closure::<dyn FnMut()>::new({
+            let runtime = runtime.clone();
+            move || {
+                runtime.in_scope(scope, || {
+                    let Some(window) = window() else {
+                        return;
+                    };
+                    // visibilitychange also fires when the tab becomes visible again,
+                    // which counts as activity
+                    if window.document().is_some_and(|document| !document.hidden()) {
+                        last_activity.set(now_ms());
+                        return;
+                    }
+
+                    // the time since the last tick was still visible
+                    let now = now_ms();
+                    if now - last_activity() < STOP_COUNTING_AFTER_MS {
+                        accumulate(now - last_sample());
+                    }
+                    last_sample.set(now);
+                    last_flush.set(now);
+
+                    if let Some(page_times) = take_snapshot() {
+                        send_beacon(
+                            &window,
+                            READ_TIMES_ROUTE,
+                            &ReadFlush {
+                                edition_id,
+                                session_id,
+                                page_times,
+                                active: active(),
+                            },
+                        );
+                    }
+                    if let Some(furthest_page) = take_progress() {
+                        send_beacon(
+                            &window,
+                            PROGRESS_ROUTE,
+                            &ProgressFlush {
+                                edition_id,
+                                session_id,
+                                furthest_page,
+                            },
+                        );
                     }
                 });
             }
         });
+        let on_activity = Closure::<dyn FnMut()>::new({
+            let runtime = runtime.clone();
+            move || {
+                runtime.in_scope(scope, || last_activity.set(now_ms()));
+            }
+        });
 
-        let handle = window()
-            .unwrap()
-            .set_interval_with_callback_and_timeout_and_arguments_0(
-                tick.as_ref().unchecked_ref(),
-                250,
-            )
-            .expect("setInterval should work");
+        let Some(window) = window() else {
+            return;
+        };
+        let Some(document) = window.document() else {
+            return;
+        };
+
+        let on_hide = Rc::new(on_hide);
+        let on_activity = Rc::new(on_activity);
+        let window: EventTarget = window.into();
+        let document: EventTarget = document.into();
+        let mut listeners = vec![
+            (document, "visibilitychange", on_hide.clone()),
+            (window.clone(), "pagehide", on_hide),
+        ];
+        for event in ACTIVITY_EVENTS {
+            listeners.push((window.clone(), event, on_activity.clone()));
+        }
 
-        interval_handle.set(Some(handle));
-        _tick.set(Some(Rc::new(tick))); // keep callback alive
+        for (target, event, callback) in &listeners {
+            let _ = target.add_event_listener_with_callback(
+                event,
+                callback.as_ref().as_ref().unchecked_ref(),
+            );
+        }
+        _listeners.set(listeners); // keep callbacks alive
     });
 
     // cleanup
@@ -219,8 +443,39 @@ fn track_reads(data: Resource<Result<ViewEdition, ServerFnError>>, edition_id: E
         if let Some(obs) = observer() {
             obs.disconnect();
         }
+        for (target, event, callback) in _listeners() {
+            let _ = target.remove_event_listener_with_callback(
+                event,
+                callback.as_ref().as_ref().unchecked_ref(),
+            );
+        }
     });
 }
 
+/// Post a JSON payload with `navigator.sendBeacon`, which is still delivered while the page unloads
+#[cfg(feature = "web")]
+fn send_beacon(window: &web_sys::Window, route: &str, payload: &impl serde::Serialize) {
+    use web_sys::{Blob, BlobPropertyBag, js_sys::Array, wasm_bindgen::JsValue};
+
+    let Ok(payload) = serde_json::to_string(payload) else {
+        return;
+    };
+
+    let options = BlobPropertyBag::new();
+    options.set_type("application/json");
+    if let Ok(blob) =
+        Blob::new_with_str_sequence_and_options(&Array::of1(&JsValue::from_str(&payload)), &options)
+    {
+        let _ = window
+            .navigator()
+            .send_beacon_with_opt_blob(route, Some(&blob));
+    }
+}
+
 #[cfg(not(feature = "web"))]
-fn track_reads(data: Resource<Result<ViewEdition, ServerFnError>>, id: EditionId) {}
+fn track_reads(
+    data: Resource<Result<EditionData, ServerFnError>>,
+    id: EditionId,
+    session_id: Uuid,
+) {
+}
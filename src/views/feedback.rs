@@ -15,14 +15,20 @@ struct FeedbackRequest {
 }
 
 #[server]
+#[tracing::instrument(skip_all, err)]
 async fn send_feedback(data: FeedbackRequest) -> Result<()> {
-    use crate::db::{db, entities::feedback};
+    use crate::{
+        db::{db, entities::feedback},
+        track_views::{NO_ID, tracked_client_id},
+    };
     use sea_orm::{EntityTrait, Set};
 
     let feedback = feedback::ActiveModel {
         content: Set(data.form.content.clone()),
         email: Set(data.form.email.clone()),
         edition_id: Set(data.edition_id),
+        // only linked with consent, so readers can look it up on the privacy page
+        client_id: Set(tracked_client_id().filter(|client_id| *client_id != NO_ID)),
         ..Default::default()
     };
 
@@ -26,6 +26,13 @@ pub use edition::Edition;
 mod feedback;
 pub use feedback::Feedback;
 
+mod analytics;
+pub use analytics::{Analytics, EditionAnalytics, EditionHeatmap, ExportStatus};
+
+mod privacy;
+pub use privacy::Privacy;
+pub(crate) use privacy::delete_stored_data;
+
 mod about;
 #[cfg(feature = "server")]
 pub use about::TEAM;
@@ -1,4 +1,4 @@
-use crate::{Route, i18n};
+use crate::{Route, attribution::ROUTE_HISTORY, i18n};
 use dioxus::prelude::*;
 
 const NAVBAR_CSS: Asset = asset!("/assets/styling/navbar.css");
@@ -12,6 +12,10 @@ const NAVBAR_CSS: Asset = asset!("/assets/styling/navbar.css");
 pub fn Navbar() -> Element {
     let mut lang = i18n::use_lang();
 
+    // remember where edition pages are navigated to from
+    let route = use_route::<Route>();
+    use_effect(use_reactive!(|route| ROUTE_HISTORY.write().visit(route)));
+
     rsx! {
         document::Link { rel: "stylesheet", href: NAVBAR_CSS }
 
@@ -21,6 +25,7 @@ pub fn Navbar() -> Element {
                 Link { to: Route::Archiv {}, "{lang.read().archive()}" }
                 Link { to: Route::About {}, "{lang.read().about_title()}" }
                 Link { to: Route::Feedback {}, "{lang.read().feedback()}" }
+                Link { to: Route::Privacy {}, "{lang.read().privacy_title()}" }
             }
 
             div { id: "language-selector",
@@ -0,0 +1,221 @@
+//! Lets readers see what is stored about their client id and delete it
+
+use crate::{EditionId, consent::WithdrawConsent, i18n};
+use dioxus::prelude::*;
+use serde::{Deserialize, Serialize};
+use time::PrimitiveDateTime;
+
+const PRIVACY_CSS: Asset = asset!("/assets/styling/privacy.css");
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct StoredRead {
+    edition_id: EditionId,
+    /// Zero-based
+    page_number: i32,
+    /// In milliseconds
+    read_time: f32,
+    timestamp: PrimitiveDateTime,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct StoredFeedback {
+    content: String,
+    email: Option<String>,
+    edition_id: Option<EditionId>,
+}
+
+/// Everything stored about a client id that a reader can recognize
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct StoredData {
+    reads: Vec<StoredRead>,
+    feedback: Vec<StoredFeedback>,
+}
+
+/// The client id of the request, if the reader has one
+#[cfg(feature = "server")]
+fn own_client_id() -> Option<uuid::Uuid> {
+    use crate::track_views::{NO_ID, get_client_id};
+
+    get_client_id()
+        .map(|client_id| client_id.0)
+        .filter(|client_id| *client_id != NO_ID)
+}
+
+/// The reads and feedback of the requesting client. None if it has no client id.
+#[server]
+#[tracing::instrument(skip_all, err)]
+async fn fetch_stored_data() -> Result<Option<StoredData>, ServerFnError> {
+    use crate::db::{
+        db,
+        entities::{feedback, reads},
+    };
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+
+    let Some(client_id) = own_client_id() else {
+        return Ok(None);
+    };
+
+    let reads = reads::Entity::find()
+        .filter(reads::Column::ClientId.eq(client_id))
+        .order_by_asc(reads::Column::Timestamp)
+        .all(db())
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to get reads: {err}")))?
+        .into_iter()
+        .map(|read| StoredRead {
+            edition_id: read.edition_id,
+            page_number: read.page_number,
+            read_time: read.read_time,
+            timestamp: read.timestamp,
+        })
+        .collect();
+
+    let feedback = feedback::Entity::find()
+        .filter(feedback::Column::ClientId.eq(client_id))
+        .order_by_asc(feedback::Column::Id)
+        .all(db())
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to get feedback: {err}")))?
+        .into_iter()
+        .map(|feedback| StoredFeedback {
+            content: feedback.content,
+            email: feedback.email,
+            edition_id: feedback.edition_id,
+        })
+        .collect();
+
+    Ok(Some(StoredData { reads, feedback }))
+}
+
+/// Delete everything tied to the requesting client id and the client id cookie.
+/// Aggregated statistics (edition views, daily rollups) can't be traced back and are kept.
+#[server]
+#[tracing::instrument(err)]
+pub(crate) async fn delete_stored_data() -> Result<(), ServerFnError> {
+    use crate::{
+        db::{
+            db,
+            entities::{
+                feedback, first_reads, flagged_traffic, read_progress, read_sources, reads,
+                sessions, views,
+            },
+        },
+        track_views::delete_client_id,
+    };
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, TransactionTrait};
+
+    let Some(client_id) = own_client_id() else {
+        return Ok(());
+    };
+
+    let delete = async {
+        let txn = db().begin().await?;
+        reads::Entity::delete_many()
+            .filter(reads::Column::ClientId.eq(client_id))
+            .exec(&txn)
+            .await?;
+        views::Entity::delete_many()
+            .filter(views::Column::ClientId.eq(client_id))
+            .exec(&txn)
+            .await?;
+        first_reads::Entity::delete_many()
+            .filter(first_reads::Column::ClientId.eq(client_id))
+            .exec(&txn)
+            .await?;
+        read_progress::Entity::delete_many()
+            .filter(read_progress::Column::ClientId.eq(client_id))
+            .exec(&txn)
+            .await?;
+        read_sources::Entity::delete_many()
+            .filter(read_sources::Column::ClientId.eq(client_id))
+            .exec(&txn)
+            .await?;
+        flagged_traffic::Entity::delete_many()
+            .filter(flagged_traffic::Column::ClientId.eq(client_id))
+            .exec(&txn)
+            .await?;
+        // the editions of a session are deleted with it
+        sessions::Entity::delete_many()
+            .filter(sessions::Column::ClientId.eq(client_id))
+            .exec(&txn)
+            .await?;
+        feedback::Entity::delete_many()
+            .filter(feedback::Column::ClientId.eq(client_id))
+            .exec(&txn)
+            .await?;
+        txn.commit().await
+    };
+    delete
+        .await
+        .map_err(|err| ServerFnError::new(format!("Failed to delete stored data: {err}")))?;
+
+    delete_client_id();
+    Ok(())
+}
+
+#[component]
+pub fn Privacy() -> Element {
+    let mut data = use_server_future(move || async move { fetch_stored_data().await })?;
+    let mut error = use_signal(|| None::<ServerFnError>);
+
+    let lang = i18n::use_lang();
+
+    rsx! {
+        document::Link { rel: "stylesheet", href: PRIVACY_CSS }
+
+        div { id: "privacy",
+            h1 { class: "text-4xl", "{lang.read().privacy_title()}" }
+            p { "{lang.read().privacy_text()}" }
+            WithdrawConsent { ondeleted: move |_| data.restart() }
+
+            h2 { class: "text-3xl", "{lang.read().stored_data()}" }
+            match &*data.read_unchecked() {
+                Some(Ok(Some(stored))) => rsx! {
+                    h3 { class: "text-2xl", "{lang.read().reads()}" }
+                    table {
+                        tr {
+                            th { "{lang.read().edition()}" }
+                            th { "{lang.read().page()}" }
+                            th { "{lang.read().read_time_minutes()}" }
+                            th { "{lang.read().time()}" }
+                        }
+                        for read in &stored.reads {
+                            tr {
+                                td { "{read.edition_id}" }
+                                td { "{read.page_number + 1}" }
+                                td { "{read.read_time / 60_000.0:.1}" }
+                                td { "{read.timestamp}" }
+                            }
+                        }
+                    }
+
+                    h3 { class: "text-2xl", "{lang.read().feedback()}" }
+                    for feedback in &stored.feedback {
+                        blockquote {
+                            p { "{feedback.content}" }
+                            if let Some(email) = &feedback.email {
+                                p { "{email}" }
+                            }
+                        }
+                    }
+
+                    button {
+                        onclick: move |_| async move {
+                            match delete_stored_data().await {
+                                Ok(()) => data.restart(),
+                                Err(err) => error.set(Some(err)),
+                            }
+                        },
+                        "{lang.read().delete_stored_data()}"
+                    }
+                    if let Some(err) = &*error.read() {
+                        p { "{lang.read().error_deleting_data()}: {err}" }
+                    }
+                },
+                Some(Ok(None)) => rsx! { p { "{lang.read().no_stored_data()}" } },
+                Some(Err(err)) => rsx! { "{lang.read().error_loading_data()}: {err}" },
+                None => rsx! { "{lang.read().loading_data()}" },
+            }
+        }
+    }
+}